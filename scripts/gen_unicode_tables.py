@@ -0,0 +1,273 @@
+#!/usr/bin/env python3
+"""Generates the tables in src/regex/unicode_tables/ from the Unicode
+Character Database.
+
+Download the UCD files listed in SOURCES from
+https://www.unicode.org/Public/<version>/ucd/ into a directory, then run
+
+    python3 scripts/gen_unicode_tables.py path/to/ucd
+
+from the root of the repository. The tables are written over the old ones and
+formatted with rustfmt. The UCD version is read from the header of
+Scripts.txt and recorded in every generated file.
+"""
+
+import os
+import re
+import subprocess
+import sys
+
+SOURCES = [
+    "UnicodeData.txt",
+    "PropertyValueAliases.txt",
+    "Scripts.txt",
+    "PropList.txt",
+    "DerivedCoreProperties.txt",
+    "CaseFolding.txt",
+]
+
+OUT_DIR = os.path.join(os.path.dirname(__file__), "..", "src", "regex", "unicode_tables")
+
+MAX_CHAR = 0x10FFFF
+SURROGATES = range(0xD800, 0xE000)
+
+
+def data_lines(path):
+    """Yields the fields of each line of a UCD file, without comments."""
+    with open(path, encoding="utf-8") as f:
+        for line in f:
+            line = line.split("#", 1)[0].strip()
+            if line:
+                yield [field.strip() for field in line.split(";")]
+
+
+def code_points(field):
+    """Parses a code point or a `lo..hi` range of them."""
+    lo, _, hi = field.partition("..")
+    return int(lo, 16), int(hi or lo, 16)
+
+
+def to_ranges(points):
+    """Turns a set of code points into sorted, merged, inclusive ranges,
+    leaving out the surrogates, which aren't chars. Ranges on both sides of
+    the surrogates are merged, as no char lies in between."""
+    ranges = []
+    for c in sorted(points):
+        if c in SURROGATES:
+            continue
+        if ranges and (ranges[-1][1] + 1 == c or (ranges[-1][1], c) == (0xD7FF, 0xE000)):
+            ranges[-1][1] = c
+        else:
+            ranges.append([c, c])
+    return [tuple(r) for r in ranges]
+
+
+def normalize(name):
+    """Same as `unicode_tables::normalize`."""
+    return "".join(c for c in name if c not in " _-").lower()
+
+
+def char_literal(c):
+    if 0x20 < c < 0x7F and chr(c) not in "'\\":
+        return "'%s'" % chr(c)
+    return "'\\u{%x}'" % c
+
+
+def ucd_version(ucd):
+    with open(os.path.join(ucd, "Scripts.txt"), encoding="utf-8") as f:
+        match = re.match(r"# Scripts-(\d+\.\d+\.\d+)\.txt", f.readline())
+    if not match:
+        sys.exit("Scripts.txt doesn't start with its version")
+    return match.group(1)
+
+
+def header(version, sources):
+    return (
+        "// Generated by scripts/gen_unicode_tables.py from the Unicode Character\n"
+        "// Database, version %s, using %s.\n"
+        "// Do not edit by hand.\n" % (version, ", ".join(sources))
+    )
+
+
+def ranges_table(name, ranges):
+    lines = ["pub const %s: &[(char, char)] = &[" % name]
+    lines += ["    (%s, %s)," % (char_literal(lo), char_literal(hi)) for lo, hi in ranges]
+    lines.append("];")
+    return "\n".join(lines) + "\n"
+
+
+def const_name(name):
+    return name.upper()
+
+
+def property_module(sets, aliases):
+    """Emits `BY_NAME`, `ALIASES` and a table for each value of a property.
+
+    `sets` maps the long name of each value to its code points, and `aliases`
+    maps each long name to all of its names. Values without any chars are
+    left out."""
+    names = sorted(name for name, points in sets.items() if to_ranges(points))
+    out = ["pub const BY_NAME: &[(&str, &[(char, char)])] = &["]
+    out += ['    ("%s", %s),' % (name, const_name(name)) for name in names]
+    out.append("];\n")
+    out.append(
+        "/// Maps every alias of a value, normalized as by `unicode_tables::normalize`,\n"
+        "/// to its name in `BY_NAME`."
+    )
+    out.append("pub const ALIASES: &[(&str, &str)] = &[")
+    pairs = sorted({(normalize(alias), name) for name in names for alias in aliases[name]})
+    out += ['    ("%s", "%s"),' % pair for pair in pairs]
+    out.append("];\n")
+    text = "\n".join(out)
+    for name in names:
+        text += "\n" + ranges_table(const_name(name), to_ranges(sets[name]))
+    return text
+
+
+def value_aliases(ucd, property):
+    """Maps the long name of each value of `property` to all of its names,
+    from PropertyValueAliases.txt."""
+    aliases = {}
+    for fields in data_lines(os.path.join(ucd, "PropertyValueAliases.txt")):
+        if fields[0] == property:
+            # The short name, the long name, then any other aliases.
+            aliases[fields[2]] = [fields[1], fields[2]] + fields[3:]
+    return aliases
+
+
+def general_categories(ucd):
+    """Maps each two letter general category to its code points."""
+    categories = {}
+    assigned = set()
+    first = None
+    for fields in data_lines(os.path.join(ucd, "UnicodeData.txt")):
+        c, name, gc = int(fields[0], 16), fields[1], fields[2]
+        # Large blocks are given by their first and last code points.
+        if name.endswith(", First>"):
+            first = c
+            continue
+        lo = first if name.endswith(", Last>") else c
+        first = None
+        points = range(lo, c + 1)
+        categories.setdefault(gc, set()).update(points)
+        assigned.update(points)
+    categories["Cn"] = set(range(MAX_CHAR + 1)) - assigned
+    return categories
+
+
+def gen_general_category(ucd):
+    categories = general_categories(ucd)
+    aliases = value_aliases(ucd, "gc")
+    short_to_long = {names[0]: long for long, names in aliases.items()}
+
+    sets = {}
+    for short, points in categories.items():
+        sets[short_to_long[short]] = points
+    # The groups of categories: every category starting with the letter, and
+    # the cased letters.
+    for short, long in short_to_long.items():
+        if len(short) == 1:
+            sets[long] = set().union(*(p for gc, p in categories.items() if gc[0] == short))
+    sets[short_to_long["LC"]] = set().union(
+        *(categories.get(gc, set()) for gc in ("Lu", "Ll", "Lt"))
+    )
+    return property_module(sets, aliases), categories
+
+
+def gen_script(ucd):
+    sets = {}
+    for fields in data_lines(os.path.join(ucd, "Scripts.txt")):
+        lo, hi = code_points(fields[0])
+        sets.setdefault(fields[1], set()).update(range(lo, hi + 1))
+    return property_module(sets, value_aliases(ucd, "sc"))
+
+
+def binary_property(ucd, file, property):
+    points = set()
+    for fields in data_lines(os.path.join(ucd, file)):
+        if fields[1] == property:
+            lo, hi = code_points(fields[0])
+            points.update(range(lo, hi + 1))
+    return points
+
+
+def gen_case_folding(ucd):
+    """Groups the chars into the equivalence classes of simple case folding,
+    which takes the common and simple mappings of CaseFolding.txt."""
+    folds_to = {}
+    for fields in data_lines(os.path.join(ucd, "CaseFolding.txt")):
+        if fields[1] in ("C", "S"):
+            folds_to[int(fields[0], 16)] = int(fields[2], 16)
+    classes = {}
+    for c, folded in folds_to.items():
+        classes.setdefault(folded, {folded}).add(c)
+
+    rows = []
+    for members in classes.values():
+        for c in members:
+            rows.append((c, sorted(members - {c})))
+    rows.sort()
+    lines = [
+        "/// Each char with a simple case folding, along with every other char in the",
+        "/// same case folding equivalence class, sorted by the first char.",
+        "pub const CASE_FOLDING_SIMPLE: &[(char, &[char])] = &[",
+    ]
+    for c, others in rows:
+        lines.append(
+            "    (%s, &[%s]),"
+            % (char_literal(c), ", ".join(char_literal(other) for other in others))
+        )
+    lines.append("];")
+    return "\n".join(lines) + "\n"
+
+
+def main():
+    if len(sys.argv) != 2:
+        sys.exit(__doc__)
+    ucd = sys.argv[1]
+    missing = [f for f in SOURCES if not os.path.exists(os.path.join(ucd, f))]
+    if missing:
+        sys.exit("missing UCD files: %s" % ", ".join(missing))
+    version = ucd_version(ucd)
+
+    general_category, categories = gen_general_category(ucd)
+    # \w follows UTS #18: alphabetic chars, marks, decimal numbers, connector
+    # punctuation and the join controls.
+    word = binary_property(ucd, "DerivedCoreProperties.txt", "Alphabetic")
+    for gc in ("Mn", "Mc", "Me", "Nd", "Pc"):
+        word |= categories.get(gc, set())
+    word |= binary_property(ucd, "PropList.txt", "Join_Control")
+
+    files = {
+        "general_category.rs": (
+            ["UnicodeData.txt", "PropertyValueAliases.txt"],
+            general_category,
+        ),
+        "script.rs": (["Scripts.txt", "PropertyValueAliases.txt"], gen_script(ucd)),
+        "perl_decimal.rs": (
+            ["UnicodeData.txt"],
+            ranges_table("DECIMAL_NUMBER", to_ranges(categories["Nd"])),
+        ),
+        "perl_space.rs": (
+            ["PropList.txt"],
+            ranges_table(
+                "WHITE_SPACE", to_ranges(binary_property(ucd, "PropList.txt", "White_Space"))
+            ),
+        ),
+        "perl_word.rs": (
+            ["UnicodeData.txt", "DerivedCoreProperties.txt", "PropList.txt"],
+            ranges_table("PERL_WORD", to_ranges(word)),
+        ),
+        "case_folding_simple.rs": (["CaseFolding.txt"], gen_case_folding(ucd)),
+    }
+    paths = []
+    for file, (sources, body) in files.items():
+        path = os.path.join(OUT_DIR, file)
+        with open(path, "w", encoding="utf-8") as f:
+            f.write(header(version, sources) + "\n" + body)
+        paths.append(path)
+    subprocess.run(["rustfmt", "--edition", "2021"] + paths, check=True)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,415 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::unicode_tables::case_folding_simple::CASE_FOLDING_SIMPLE;
+
+/// A set of chars, stored as sorted, non-overlapping and non-adjacent
+/// inclusive ranges.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CharSet {
+    ranges: Vec<(char, char)>,
+}
+
+/// Returns the char right after `c`, skipping over the surrogate gap.
+fn next_char(c: char) -> Option<char> {
+    match c {
+        '\u{D7FF}' => Some('\u{E000}'),
+        c => char::from_u32(u32::from(c) + 1),
+    }
+}
+
+/// Returns the char right before `c`, skipping over the surrogate gap.
+fn prev_char(c: char) -> Option<char> {
+    match c {
+        '\u{E000}' => Some('\u{D7FF}'),
+        c => u32::from(c).checked_sub(1).and_then(char::from_u32),
+    }
+}
+
+impl CharSet {
+    pub fn new() -> Self {
+        CharSet { ranges: vec![] }
+    }
+
+    pub fn from_char(c: char) -> Self {
+        CharSet {
+            ranges: vec![(c, c)],
+        }
+    }
+
+    /// Builds a set out of arbitrary inclusive ranges, which may overlap or
+    /// be out of order. Ranges with `lo > hi` are ignored.
+    pub fn from_ranges<I: IntoIterator<Item = (char, char)>>(ranges: I) -> Self {
+        let mut ranges: Vec<_> = ranges.into_iter().filter(|(lo, hi)| lo <= hi).collect();
+        ranges.sort_unstable();
+
+        let mut merged: Vec<(char, char)> = Vec::with_capacity(ranges.len());
+        for (lo, hi) in ranges {
+            if let Some(last) = merged.last_mut() {
+                let touches = match next_char(last.1) {
+                    Some(c) => lo <= c,
+                    // `last` already runs up to the last char.
+                    None => true,
+                };
+                if touches {
+                    last.1 = last.1.max(hi);
+                    continue;
+                }
+            }
+            merged.push((lo, hi));
+        }
+        CharSet { ranges: merged }
+    }
+
+    /// The set of every Unicode scalar value.
+    pub fn full() -> Self {
+        CharSet {
+            ranges: vec![('\0', char::MAX)],
+        }
+    }
+
+    /// The set of the bytes from `lo` to `hi`. Automata reading bytes rather
+    /// than chars label their transitions with sets of bytes, each byte
+    /// standing for itself as the char from U+00 to U+FF with the same value.
+    pub fn from_bytes(lo: u8, hi: u8) -> Self {
+        CharSet {
+            ranges: vec![(char::from(lo), char::from(hi))],
+        }
+    }
+
+    /// The set of every byte, as in `from_bytes`.
+    pub fn all_bytes() -> Self {
+        CharSet::from_bytes(0, 0xFF)
+    }
+
+    pub fn ranges(&self) -> &[(char, char)] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.ranges
+            .binary_search_by(|&(lo, hi)| {
+                if hi < c {
+                    std::cmp::Ordering::Less
+                } else if lo > c {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .is_ok()
+    }
+
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.ranges.iter().flat_map(|&(lo, hi)| lo..=hi)
+    }
+
+    pub fn union(&self, other: &CharSet) -> CharSet {
+        CharSet::from_ranges(self.ranges.iter().chain(other.ranges.iter()).copied())
+    }
+
+    pub fn intersection(&self, other: &CharSet) -> CharSet {
+        let mut ranges = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (lo1, hi1) = self.ranges[i];
+            let (lo2, hi2) = other.ranges[j];
+            let (lo, hi) = (lo1.max(lo2), hi1.min(hi2));
+            if lo <= hi {
+                ranges.push((lo, hi));
+            }
+            if hi1 < hi2 {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        CharSet { ranges }
+    }
+
+    pub fn difference(&self, other: &CharSet) -> CharSet {
+        self.intersection(&other.negate())
+    }
+
+    /// Returns the set along with every char equal to one in it under Unicode
+    /// simple case folding, so `[a-c]` becomes `[A-Ca-c]`, and `k` also picks
+    /// up the Kelvin sign.
+    pub fn case_fold(&self) -> CharSet {
+        let mut ranges = self.ranges.clone();
+        for &(lo, hi) in &self.ranges {
+            let start = CASE_FOLDING_SIMPLE.partition_point(|&(c, _)| c < lo);
+            for &(_, others) in CASE_FOLDING_SIMPLE[start..]
+                .iter()
+                .take_while(|&&(c, _)| c <= hi)
+            {
+                ranges.extend(others.iter().map(|&c| (c, c)));
+            }
+        }
+        CharSet::from_ranges(ranges)
+    }
+
+    /// Returns every Unicode scalar value not in this set.
+    pub fn negate(&self) -> CharSet {
+        let mut ranges = vec![];
+        let mut lo = Some('\0');
+        for &(range_lo, range_hi) in &self.ranges {
+            if let Some(lo) = lo {
+                if let Some(hi) = prev_char(range_lo) {
+                    if lo <= hi {
+                        ranges.push((lo, hi));
+                    }
+                }
+            }
+            lo = next_char(range_hi);
+        }
+        if let Some(lo) = lo {
+            ranges.push((lo, char::MAX));
+        }
+        CharSet { ranges }
+    }
+}
+
+/// Splits the chars covered by `sets` into disjoint equivalence classes, such
+/// that two chars share a class iff every set contains either both or neither
+/// of them.
+///
+/// Returns the classes, along with the indices of the classes making up each
+/// of the sets. Automata can then work over class indices instead of chars, so
+/// that a transition over a huge range is as cheap as one over a single char.
+pub fn split_classes(sets: &[&CharSet]) -> (Vec<CharSet>, Vec<Vec<usize>>) {
+    // Code points at which set membership may change, as (point, set, is_start).
+    // Working over `u32` lets an end point lie past `char::MAX`.
+    let mut events = vec![];
+    for (i, set) in sets.iter().enumerate() {
+        for &(lo, hi) in set.ranges() {
+            events.push((u32::from(lo), i, true));
+            events.push((u32::from(hi) + 1, i, false));
+        }
+    }
+    events.sort_unstable();
+
+    let mut signature_to_class: BTreeMap<Vec<usize>, usize> = BTreeMap::new();
+    let mut class_ranges: Vec<Vec<(char, char)>> = vec![];
+    let mut set_classes: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); sets.len()];
+
+    let mut active = BTreeSet::new();
+    let mut k = 0;
+    while k < events.len() {
+        let point = events[k].0;
+        while k < events.len() && events[k].0 == point {
+            let (_, i, is_start) = events[k];
+            if is_start {
+                active.insert(i);
+            } else {
+                active.remove(&i);
+            }
+            k += 1;
+        }
+        if active.is_empty() || k == events.len() {
+            continue;
+        }
+
+        // The segment [point, next point) lies in exactly the active sets.
+        // Segments inside the surrogate gap have no chars and are skipped.
+        let seg_end = events[k].0 - 1;
+        let surrogates = 0xD800..=0xDFFF;
+        let lo = if surrogates.contains(&point) {
+            0xE000
+        } else {
+            point
+        };
+        let hi = if surrogates.contains(&seg_end) {
+            0xD7FF
+        } else {
+            seg_end
+        };
+        if lo > hi {
+            continue;
+        }
+        let (lo, hi) = (char::from_u32(lo).unwrap(), char::from_u32(hi).unwrap());
+
+        let signature: Vec<usize> = active.iter().copied().collect();
+        let next_id = class_ranges.len();
+        let class = *signature_to_class.entry(signature).or_insert(next_id);
+        if class == next_id {
+            class_ranges.push(vec![]);
+        }
+        class_ranges[class].push((lo, hi));
+        for &i in &active {
+            set_classes[i].insert(class);
+        }
+    }
+
+    (
+        class_ranges.into_iter().map(CharSet::from_ranges).collect(),
+        set_classes
+            .into_iter()
+            .map(|classes| classes.into_iter().collect())
+            .collect(),
+    )
+}
+
+/// The number of low bits of a code point picking it out of its block in a
+/// `ClassMap`.
+const BLOCK_BITS: u32 = 8;
+
+/// Looks up which of a list of disjoint sets a char is in, in constant time.
+///
+/// The code points are split into blocks of 256, and each block points at a
+/// row holding the set of every char in it. Blocks with the same row share
+/// it, so the many blocks lying inside a single set only cost one row.
+#[derive(Debug, Clone)]
+pub struct ClassMap {
+    /// The offset into `rows` of the row of each block.
+    blocks: Vec<u32>,
+    rows: Vec<u32>,
+}
+
+impl ClassMap {
+    /// Maps the chars in each of `classes` to its index, and every other
+    /// char to `rest`.
+    pub fn new(classes: &[CharSet], rest: usize) -> ClassMap {
+        let mut ranges: Vec<_> = classes
+            .iter()
+            .enumerate()
+            .flat_map(|(i, set)| {
+                let i = u32::try_from(i).unwrap();
+                set.ranges()
+                    .iter()
+                    .map(move |&(lo, hi)| (u32::from(lo), u32::from(hi), i))
+            })
+            .collect();
+        ranges.sort_unstable();
+        let rest = u32::try_from(rest).unwrap();
+
+        let block_len = 1 << BLOCK_BITS;
+        let block_count = (u32::from(char::MAX) >> BLOCK_BITS) + 1;
+        let mut blocks = vec![];
+        let mut rows = vec![];
+        let mut row_offsets: BTreeMap<Vec<u32>, u32> = BTreeMap::new();
+        // The offset of the row of the blocks lying in a single class, by
+        // class.
+        let mut uniform_offsets: BTreeMap<u32, u32> = BTreeMap::new();
+        // The first range not ending before the current block.
+        let mut k = 0;
+        let mut block = 0;
+        while block < block_count {
+            let (lo, hi) = (block << BLOCK_BITS, (block << BLOCK_BITS) + block_len - 1);
+            while k < ranges.len() && ranges[k].1 < lo {
+                k += 1;
+            }
+            // Blocks in a single class come in runs, up to but not including
+            // the block where the class ends.
+            let uniform = match ranges.get(k) {
+                None => Some((rest, block_count)),
+                Some(&(range_lo, _, _)) if range_lo > hi => Some((rest, range_lo >> BLOCK_BITS)),
+                Some(&(range_lo, range_hi, class)) if range_lo <= lo && range_hi >= hi => {
+                    Some((class, (range_hi + 1) >> BLOCK_BITS))
+                }
+                Some(_) => None,
+            };
+            if let Some((class, end)) = uniform {
+                let offset = *uniform_offsets.entry(class).or_insert_with(|| {
+                    let offset = u32::try_from(rows.len()).unwrap();
+                    rows.extend((0..block_len).map(|_| class));
+                    offset
+                });
+                blocks.extend((block..end).map(|_| offset));
+                block = end;
+                continue;
+            }
+
+            let mut row = vec![rest; usize::try_from(block_len).unwrap()];
+            for &(range_lo, range_hi, class) in ranges[k..].iter().take_while(|r| r.0 <= hi) {
+                for c in range_lo.max(lo)..=range_hi.min(hi) {
+                    row[usize::try_from(c - lo).unwrap()] = class;
+                }
+            }
+            let offset = *row_offsets.entry(row).or_insert_with_key(|row| {
+                let offset = u32::try_from(rows.len()).unwrap();
+                rows.extend_from_slice(row);
+                offset
+            });
+            blocks.push(offset);
+            block += 1;
+        }
+        ClassMap { blocks, rows }
+    }
+
+    pub fn get(&self, c: char) -> usize {
+        let c = u32::from(c);
+        let row = self.blocks[usize::try_from(c >> BLOCK_BITS).unwrap()];
+        let low = c & ((1 << BLOCK_BITS) - 1);
+        usize::try_from(self.rows[usize::try_from(row + low).unwrap()]).unwrap()
+    }
+}
+
+/// Prints the set as a bracket class which parses back to the same set.
+impl fmt::Display for CharSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_char(f: &mut fmt::Formatter<'_>, c: char) -> fmt::Result {
+            match c {
+                ']' | '[' | '-' | '^' | '\\' => write!(f, "\\{}", c),
+                '\n' => write!(f, "\\n"),
+                '\t' => write!(f, "\\t"),
+                '\r' => write!(f, "\\r"),
+                c if c.is_control() => write!(f, "\\u{{{:x}}}", u32::from(c)),
+                c => write!(f, "{}", c),
+            }
+        }
+
+        // Sets containing most chars, like those of `.` or `[^"]`, read better
+        // written as negated classes.
+        let negated = self.negate();
+        let (prefix, ranges) = if negated.ranges.len() < self.ranges.len()
+            || (negated.ranges.len() == self.ranges.len() && self.contains('\0'))
+        {
+            ("[^", &negated.ranges)
+        } else {
+            ("[", &self.ranges)
+        };
+
+        write!(f, "{}", prefix)?;
+        for &(lo, hi) in ranges {
+            write_char(f, lo)?;
+            if lo != hi {
+                if next_char(lo) != Some(hi) {
+                    write!(f, "-")?;
+                }
+                write_char(f, hi)?;
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+/// Prints a set of bytes, as in `CharSet::from_bytes`, as a bracket class of
+/// hex escapes, eg. `[\x00-\x7F\xC3]`.
+pub struct ByteClass<'a>(pub &'a CharSet);
+
+impl fmt::Debug for ByteClass<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for &(lo, hi) in self.0.ranges() {
+            write!(f, "\\x{:02X}", u32::from(lo))?;
+            if lo != hi {
+                write!(f, "-\\x{:02X}", u32::from(hi))?;
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+impl fmt::Debug for CharSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
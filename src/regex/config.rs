@@ -0,0 +1,87 @@
+/// Options that control how a pattern is parsed and compiled.
+///
+/// The setters consume and return the config, so options can be chained off
+/// of `Config::default()`.
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    repetition_limit: u32,
+    dot_matches_new_line: bool,
+    unicode: bool,
+    case_insensitive: bool,
+    multi_line: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            repetition_limit: 1000,
+            dot_matches_new_line: false,
+            unicode: true,
+            case_insensitive: false,
+            multi_line: false,
+        }
+    }
+}
+
+impl Config {
+    /// Sets the largest count accepted in a bounded repetition like `a{m,n}`.
+    ///
+    /// Every repetition is expanded into copies of its operand, so this keeps
+    /// a single pattern from producing an arbitrarily large ε-NFA. Nested
+    /// repetitions multiply, so the limit applies to the product of their
+    /// counts, and `(a{100}){100}` is rejected under the default of 1000.
+    pub fn repetition_limit(mut self, limit: u32) -> Self {
+        self.repetition_limit = limit;
+        self
+    }
+
+    pub fn get_repetition_limit(&self) -> u32 {
+        self.repetition_limit
+    }
+
+    /// Sets whether `.` also matches `\n`, as if the whole pattern were
+    /// preceded by `(?s)`. It matches any other char either way.
+    pub fn dot_matches_new_line(mut self, yes: bool) -> Self {
+        self.dot_matches_new_line = yes;
+        self
+    }
+
+    pub fn get_dot_matches_new_line(&self) -> bool {
+        self.dot_matches_new_line
+    }
+
+    /// Sets whether the shorthand classes `\d`, `\w` and `\s` use their
+    /// Unicode definitions. When off, they only match ASCII chars.
+    pub fn unicode(mut self, yes: bool) -> Self {
+        self.unicode = yes;
+        self
+    }
+
+    pub fn get_unicode(&self) -> bool {
+        self.unicode
+    }
+
+    /// Sets whether letters match regardless of case, as if the whole pattern
+    /// were preceded by `(?i)`. Chars are matched up to Unicode simple case
+    /// folding, so `k` also matches the Kelvin sign `K`.
+    pub fn case_insensitive(mut self, yes: bool) -> Self {
+        self.case_insensitive = yes;
+        self
+    }
+
+    pub fn get_case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+
+    /// Sets whether `^` and `$` match at the start and end of every line, as
+    /// if the whole pattern were preceded by `(?m)`, rather than only at the
+    /// start and end of the text. Lines are separated by `\n`.
+    pub fn multi_line(mut self, yes: bool) -> Self {
+        self.multi_line = yes;
+        self
+    }
+
+    pub fn get_multi_line(&self) -> bool {
+        self.multi_line
+    }
+}
@@ -1,11 +1,16 @@
 use std::{
-    cmp::Ordering,
+    cmp::{Ordering, Reverse},
     collections::{BTreeMap, BTreeSet, VecDeque},
     fmt,
 };
 
-use super::parsing::{ExprUnit, RegexOp};
-use anyhow::{Error, Result};
+use super::{
+    charset::{split_classes, ByteClass, CharSet, ClassMap},
+    config::Config,
+    parsing::{Anchor, Ast, AstKind, Flags, RegexError, RegexErrorKind, RepetitionError, Span},
+    utf8::utf8_sequences,
+};
+use anyhow::Result;
 use petgraph::{
     algo::tarjan_scc,
     dot::Dot,
@@ -16,79 +21,99 @@ use petgraph::{
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
-#[derive(Serialize, Deserialize, Clone, Copy)]
-enum ENfaEdge {
-    Epsilon,
-    Char(char),
+/// A zero-width assertion about the text around a position.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Look {
+    TextStart,
+    TextEnd,
+    /// At the start of the text or right after a `\n`.
+    LineStart,
+    /// At the end of the text or right before a `\n`.
+    LineEnd,
 }
 
-impl fmt::Debug for ENfaEdge {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                ENfaEdge::Epsilon => 'ε',
-                ENfaEdge::Char(c) => *c,
-            }
-        )
-    }
+/// What the char before a position is, as far as the assertions are
+/// concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Before {
+    TextStart,
+    NewLine,
+    Other,
 }
 
-impl TryFrom<ENfaEdge> for char {
-    type Error = ENfaEdge;
+/// What the char after a position has to be for the assertions passed on the
+/// way there to hold. Each requirement is stricter than the ones before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum After {
+    Any,
+    NewLineOrEnd,
+    End,
+}
 
-    fn try_from(other: ENfaEdge) -> Result<Self, Self::Error> {
-        match other {
-            ENfaEdge::Char(c) => Ok(c),
-            v => Err(v),
+impl Look {
+    /// Returns what the next char has to be for the assertion to hold at a
+    /// position following `before`, or `None` if it can't hold there at all.
+    fn check(self, before: Before) -> Option<After> {
+        match self {
+            Look::TextStart if before == Before::TextStart => Some(After::Any),
+            Look::LineStart if before != Before::Other => Some(After::Any),
+            Look::TextStart | Look::LineStart => None,
+            Look::TextEnd => Some(After::End),
+            Look::LineEnd => Some(After::NewLineOrEnd),
         }
     }
-}
 
-impl PartialEq<ENfaEdge> for ENfaEdge {
-    fn eq(&self, other: &ENfaEdge) -> bool {
+    /// Returns whether the assertion holds between `prev` and `next`, the
+    /// chars around a position, with `None` standing for an end of the text.
+    fn holds(self, prev: Option<char>, next: Option<char>) -> bool {
         match self {
-            ENfaEdge::Epsilon => match other {
-                ENfaEdge::Epsilon => true,
-                ENfaEdge::Char(_) => false,
-            },
-            ENfaEdge::Char(c1) => match other {
-                ENfaEdge::Epsilon => false,
-                ENfaEdge::Char(c2) => c1.eq(c2),
-            },
+            Look::TextStart => prev.is_none(),
+            Look::TextEnd => next.is_none(),
+            Look::LineStart => matches!(prev, None | Some('\n')),
+            Look::LineEnd => matches!(next, None | Some('\n')),
         }
     }
 }
 
-impl Eq for ENfaEdge {}
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum ENfaEdge {
+    Epsilon,
+    Char(char),
+    Class(CharSet),
+    /// A transition over any byte from the first to the second, in automata
+    /// reading UTF-8 bytes rather than chars. See `ENfa::to_utf8`.
+    Bytes(u8, u8),
+    Look(Look),
+    /// An ε transition recording the current position in a capture slot.
+    /// Group `i` starts at slot `2 * i` and ends at slot `2 * i + 1`.
+    Tag(u32),
+}
 
-impl PartialOrd<ENfaEdge> for ENfaEdge {
-    fn partial_cmp(&self, other: &ENfaEdge) -> Option<Ordering> {
+impl fmt::Debug for ENfaEdge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ENfaEdge::Epsilon => match other {
-                ENfaEdge::Epsilon => Some(Ordering::Equal),
-                ENfaEdge::Char(_) => Some(Ordering::Less),
-            },
-            ENfaEdge::Char(c1) => match other {
-                ENfaEdge::Epsilon => Some(Ordering::Greater),
-                ENfaEdge::Char(c2) => c1.partial_cmp(c2),
-            },
+            ENfaEdge::Epsilon => write!(f, "ε"),
+            ENfaEdge::Char(c) => write!(f, "{}", c),
+            ENfaEdge::Class(set) => write!(f, "{:?}", set),
+            ENfaEdge::Bytes(lo, hi) if lo == hi => write!(f, "\\x{:02X}", lo),
+            ENfaEdge::Bytes(lo, hi) => write!(f, "[\\x{:02X}-\\x{:02X}]", lo, hi),
+            ENfaEdge::Look(Look::TextStart) => write!(f, "^"),
+            ENfaEdge::Look(Look::TextEnd) => write!(f, "$"),
+            ENfaEdge::Look(Look::LineStart) => write!(f, "(?m)^"),
+            ENfaEdge::Look(Look::LineEnd) => write!(f, "(?m)$"),
+            ENfaEdge::Tag(slot) if slot % 2 == 0 => write!(f, "({}", slot / 2),
+            ENfaEdge::Tag(slot) => write!(f, "){}", slot / 2),
         }
     }
 }
 
-impl Ord for ENfaEdge {
-    fn cmp(&self, other: &Self) -> Ordering {
-        match self {
-            ENfaEdge::Epsilon => match other {
-                ENfaEdge::Epsilon => Ordering::Equal,
-                ENfaEdge::Char(_) => Ordering::Less,
-            },
-            ENfaEdge::Char(c1) => match other {
-                ENfaEdge::Epsilon => Ordering::Greater,
-                ENfaEdge::Char(c2) => c1.cmp(c2),
-            },
+impl TryFrom<ENfaEdge> for char {
+    type Error = ENfaEdge;
+
+    fn try_from(other: ENfaEdge) -> Result<Self, Self::Error> {
+        match other {
+            ENfaEdge::Char(c) => Ok(c),
+            v => Err(v),
         }
     }
 }
@@ -115,12 +140,12 @@ impl ENfa {
     }
 }
 
-fn gen_char_nfa(c: char) -> ENfa {
+fn gen_edge_nfa(edge: ENfaEdge) -> ENfa {
     let mut graph = GraphMap::with_capacity(2, 1);
     let start_node = graph.add_node(0);
     let final_node = graph.add_node(1);
     let mut edge_map = BTreeSet::new();
-    edge_map.insert(ENfaEdge::Char(c));
+    edge_map.insert(edge);
     graph.add_edge(start_node, final_node, edge_map);
     ENfa {
         graph,
@@ -129,6 +154,50 @@ fn gen_char_nfa(c: char) -> ENfa {
     }
 }
 
+fn gen_char_nfa(c: char) -> ENfa {
+    gen_edge_nfa(ENfaEdge::Char(c))
+}
+
+/// Generates an ε-NFA with a single transition over every char in `set`.
+pub fn gen_class_nfa(set: CharSet) -> ENfa {
+    gen_edge_nfa(ENfaEdge::Class(set))
+}
+
+/// Generates an ε-NFA with a single transition over every byte from `lo` to
+/// `hi`.
+pub fn gen_byte_nfa(lo: u8, hi: u8) -> ENfa {
+    gen_edge_nfa(ENfaEdge::Bytes(lo, hi))
+}
+
+/// Generates an ε-NFA accepting only the empty string.
+fn gen_epsilon_nfa() -> ENfa {
+    let mut graph = GraphMap::with_capacity(1, 0);
+    let node = graph.add_node(0);
+    ENfa {
+        graph,
+        start: node,
+        fin: vec![node],
+    }
+}
+
+/// Generates an ε-NFA accepting any string at all, `[^]*`.
+pub fn gen_any_string_nfa() -> ENfa {
+    let mut nfa = gen_class_nfa(CharSet::full());
+    star_nfa(&mut nfa);
+    nfa
+}
+
+/// Generates an ε-NFA accepting nothing at all.
+fn gen_empty_nfa() -> ENfa {
+    let mut graph = GraphMap::with_capacity(1, 0);
+    let node = graph.add_node(0);
+    ENfa {
+        graph,
+        start: node,
+        fin: vec![],
+    }
+}
+
 fn merge_graphmaps(g1: &mut ENfaGraph, g2: ENfaGraph) {
     let ord1 = u32::try_from(g1.node_count()).unwrap();
     let fin_ord = u32::try_from(g1.node_count() + g2.node_count()).unwrap();
@@ -175,6 +244,52 @@ pub fn union_nfa(mut nfa1: ENfa, mut nfa2: ENfa) -> ENfa {
     nfa1
 }
 
+/// Joins the automata under a new shared start state, with ε transitions to
+/// each of their start states.
+///
+/// Unlike with `union_nfa`, their final states are kept apart, and returned
+/// for each automaton in turn, so that it can still be told which of them
+/// accepted.
+pub fn union_all_nfa(nfas: Vec<ENfa>) -> (ENfa, Vec<Vec<u32>>) {
+    let mut union = gen_empty_nfa();
+    let mut fins = vec![];
+    for nfa in nfas {
+        let ord = u32::try_from(union.graph.node_count()).unwrap();
+        merge_graphmaps(&mut union.graph, nfa.graph);
+        union.add_edge(union.start, ord + nfa.start, ENfaEdge::Epsilon);
+        let fin: Vec<_> = nfa.fin.iter().map(|v| ord + v).collect();
+        union.fin.extend(&fin);
+        fins.push(fin);
+    }
+    (union, fins)
+}
+
+/// Joins the automata with `union_all_nfa` and determinizes the result with
+/// the subset construction. Along with the DFA, returns the indices of the
+/// automata accepting in each of its states, in increasing order.
+pub fn union_all_dfa(nfas: Vec<ENfa>) -> (Nfa, Vec<Vec<usize>>) {
+    let (union, fins) = union_all_nfa(nfas);
+    let nfa_of: BTreeMap<_, _> = fins
+        .iter()
+        .enumerate()
+        .flat_map(|(i, fin)| fin.iter().map(move |&v| (v, i)))
+        .collect();
+    let (nfa, nfa_accepts) = union.to_nfa_with_accepts();
+    let (dfa, subsets) = nfa.subset_construction_with_subsets();
+    let accepts = subsets
+        .iter()
+        .map(|subset| {
+            let accepting: BTreeSet<_> = subset
+                .iter()
+                .flat_map(|&u| &nfa_accepts[usize::try_from(u).unwrap()])
+                .map(|v| nfa_of[v])
+                .collect();
+            accepting.into_iter().collect()
+        })
+        .collect();
+    (dfa, accepts)
+}
+
 pub fn concat_nfa(mut nfa1: ENfa, mut nfa2: ENfa) -> ENfa {
     let (effort_now, effort_after) = (
         nfa2.graph.node_count() + nfa2.graph.edge_count() + nfa1.fin.len() + nfa2.fin.len(),
@@ -223,55 +338,217 @@ pub fn star_nfa(nfa: &mut ENfa) {
     nfa.fin = vec![fin_node];
 }
 
-pub fn gen_epsilon_nfa_from_expr(expr: &[ExprUnit]) -> Result<ENfa> {
-    let mut graph_stk = vec![];
+pub fn plus_nfa(nfa: &mut ENfa) {
+    let start_node = u32::try_from(nfa.graph.node_count()).unwrap();
+    nfa.graph.add_node(start_node);
+    let fin_node = u32::try_from(nfa.graph.node_count()).unwrap();
+    nfa.graph.add_node(fin_node);
 
-    if expr.is_empty() {
-        return Err(Error::msg("Invalid postfix expression: empty expression"));
+    // Same as the kleene star, minus the edge skipping the inner automaton.
+    nfa.add_edge(start_node, nfa.start, ENfaEdge::Epsilon);
+    for j in nfa.fin.clone().iter() {
+        nfa.add_edge(*j, nfa.start, ENfaEdge::Epsilon);
+        nfa.add_edge(*j, fin_node, ENfaEdge::Epsilon);
     }
 
-    for unit in expr {
-        match unit {
-            ExprUnit::Char(c) => {
-                graph_stk.push(gen_char_nfa(*c));
-            }
-            ExprUnit::Op(RegexOp::Union) => {
-                if graph_stk.len() < 2 {
-                    return Err(Error::msg(
-                        "Invalid postfix expression: not enough operands for union",
-                    ));
-                }
-                let op2 = graph_stk.pop().unwrap();
-                let op1 = graph_stk.pop().unwrap();
-                graph_stk.push(union_nfa(op1, op2));
-            }
-            ExprUnit::Op(RegexOp::Concat) => {
-                if graph_stk.len() < 2 {
-                    return Err(Error::msg(
-                        "Invalid postfix expression: not enough operands for concat",
-                    ));
-                }
-                let op2 = graph_stk.pop().unwrap();
-                let op1 = graph_stk.pop().unwrap();
-                graph_stk.push(concat_nfa(op1, op2));
-            }
-            ExprUnit::Op(RegexOp::Star) => {
-                if graph_stk.len() < 1 {
-                    return Err(Error::msg(
-                        "Invalid postfix expression: not enough operands for kleene star",
-                    ));
-                }
-                let mut op1 = graph_stk.pop().unwrap();
-                star_nfa(&mut op1);
-                graph_stk.push(op1);
-            }
-        }
+    nfa.start = start_node;
+    nfa.fin = vec![fin_node];
+}
+
+/// Wraps the automaton in tagged ε transitions recording where capture group
+/// `index` starts and ends.
+pub fn capture_nfa(nfa: &mut ENfa, index: u32) {
+    let start_node = u32::try_from(nfa.graph.node_count()).unwrap();
+    nfa.graph.add_node(start_node);
+    let fin_node = u32::try_from(nfa.graph.node_count()).unwrap();
+    nfa.graph.add_node(fin_node);
+
+    nfa.add_edge(start_node, nfa.start, ENfaEdge::Tag(2 * index));
+    for j in nfa.fin.clone().iter() {
+        nfa.add_edge(*j, fin_node, ENfaEdge::Tag(2 * index + 1));
+    }
+
+    nfa.start = start_node;
+    nfa.fin = vec![fin_node];
+}
+
+pub fn optional_nfa(nfa: &mut ENfa) {
+    let start_node = u32::try_from(nfa.graph.node_count()).unwrap();
+    nfa.graph.add_node(start_node);
+    let fin_node = u32::try_from(nfa.graph.node_count()).unwrap();
+    nfa.graph.add_node(fin_node);
+
+    nfa.add_edge(start_node, nfa.start, ENfaEdge::Epsilon);
+    nfa.add_edge(start_node, fin_node, ENfaEdge::Epsilon);
+    for j in nfa.fin.clone().iter() {
+        nfa.add_edge(*j, fin_node, ENfaEdge::Epsilon);
     }
 
-    if graph_stk.len() != 1 {
-        return Err(Error::msg("Invalid postfix expression: too few operands"));
+    nfa.start = start_node;
+    nfa.fin = vec![fin_node];
+}
+
+/// Expands a bounded repetition into copies of `nfa`.
+///
+/// `x{m,n}` becomes `m` copies of `x` followed by `n - m` nested optional
+/// copies, as in `xx(x(x)?)?` for `x{2,4}`, and `x{m,}` becomes `m` copies of
+/// `x` followed by `x*`.
+pub fn repeat_nfa(nfa: ENfa, min: u32, max: Option<u32>) -> ENfa {
+    let tail = match max {
+        None => {
+            let mut tail = nfa.clone();
+            star_nfa(&mut tail);
+            Some(tail)
+        }
+        Some(max) => (min..max).fold(None, |tail, _| {
+            let mut copy = match tail {
+                None => nfa.clone(),
+                Some(tail) => concat_nfa(nfa.clone(), tail),
+            };
+            optional_nfa(&mut copy);
+            Some(copy)
+        }),
+    };
+
+    let head = (0..min).fold(None, |head, _| match head {
+        None => Some(nfa.clone()),
+        Some(head) => Some(concat_nfa(head, nfa.clone())),
+    });
+
+    match (head, tail) {
+        (Some(head), Some(tail)) => concat_nfa(head, tail),
+        (Some(nfa), None) | (None, Some(nfa)) => nfa,
+        (None, None) => gen_epsilon_nfa(),
     }
-    Ok(graph_stk.pop().unwrap())
+}
+
+/// Builds the Thompson ε-NFA of a parsed regex.
+pub fn gen_epsilon_nfa_from_expr(ast: &Ast, config: &Config) -> Result<ENfa, RegexError> {
+    gen_epsilon_nfa_with_flags(ast, config, Flags::from_config(config), 1)
+}
+
+/// Builds the ε-NFA of `ast` with `flags` in effect, until a flag group in
+/// `ast` changes them.
+///
+/// The bounded repetitions around `ast` make `copies` copies of it in all,
+/// and a repetition inside of it multiplies that further. The product, rather
+/// than each count on its own, is held to the repetition limit, so that
+/// nesting repetitions can't blow the ε-NFA up either.
+fn gen_epsilon_nfa_with_flags(
+    ast: &Ast,
+    config: &Config,
+    flags: Flags,
+    copies: u32,
+) -> Result<ENfa, RegexError> {
+    let nfa = match &ast.kind {
+        // Case insensitivity is dealt with here rather than in the parser,
+        // by widening each literal into the set of its case variants. Only
+        // negated classes are folded by the tokenizer, before negating them.
+        AstKind::Char(c) if flags.case_insensitive => {
+            let set = CharSet::from_char(*c).case_fold();
+            match set.ranges() {
+                [(lo, hi)] if lo == hi => gen_char_nfa(*lo),
+                _ => gen_class_nfa(set),
+            }
+        }
+        AstKind::Char(c) => gen_char_nfa(*c),
+        AstKind::Class(set) if set.is_empty() => gen_empty_nfa(),
+        AstKind::Class(set) if flags.case_insensitive => gen_class_nfa(set.case_fold()),
+        AstKind::Class(set) => gen_class_nfa(set.clone()),
+        AstKind::Epsilon => gen_epsilon_nfa(),
+        AstKind::Dot => {
+            let mut set = CharSet::full();
+            if !flags.dot_matches_new_line {
+                set = set.difference(&CharSet::from_char('\n'));
+            }
+            gen_class_nfa(set)
+        }
+        AstKind::Anchor(anchor) => gen_edge_nfa(ENfaEdge::Look(match (anchor, flags.multi_line) {
+            (Anchor::Start, false) => Look::TextStart,
+            (Anchor::End, false) => Look::TextEnd,
+            (Anchor::Start, true) => Look::LineStart,
+            (Anchor::End, true) => Look::LineEnd,
+        })),
+        AstKind::Union(asts) | AstKind::Concat(asts) => {
+            let combine = match ast.kind {
+                AstKind::Union(_) => union_nfa,
+                _ => concat_nfa,
+            };
+            let mut nfas = asts
+                .iter()
+                .map(|ast| gen_epsilon_nfa_with_flags(ast, config, flags, copies));
+            let first = nfas.next().unwrap_or_else(|| Ok(gen_epsilon_nfa()))?;
+            nfas.try_fold(first, |nfa, next| Ok::<_, RegexError>(combine(nfa, next?)))?
+        }
+        AstKind::Flags { flags, ast } => gen_epsilon_nfa_with_flags(ast, config, *flags, copies)?,
+        AstKind::Group { index, ast, .. } => {
+            let mut nfa = gen_epsilon_nfa_with_flags(ast, config, flags, copies)?;
+            capture_nfa(&mut nfa, *index);
+            nfa
+        }
+        // Boolean operations have no Thompson construction, so their operands
+        // are determinized, combined as DFAs and brought back as ε-NFAs. Any
+        // groups in them are lost along the way, and never capture.
+        AstKind::Intersection(asts) => {
+            let mut dfas = asts
+                .iter()
+                .map(|ast| Ok(gen_epsilon_nfa_with_flags(ast, config, flags, copies)?.to_dfa()));
+            let first = dfas
+                .next()
+                .unwrap_or_else(|| Ok(gen_epsilon_nfa().to_dfa()))?;
+            dfas.try_fold(first, |dfa, next| {
+                Ok::<_, RegexError>(dfa.intersection(next?).minimized_dfa())
+            })?
+            .to_enfa()
+        }
+        AstKind::Difference(left, right) => {
+            let left = gen_epsilon_nfa_with_flags(left, config, flags, copies)?.to_dfa();
+            let right = gen_epsilon_nfa_with_flags(right, config, flags, copies)?.to_dfa();
+            left.difference(right).minimized_dfa().to_enfa()
+        }
+        AstKind::Complement(ast) => gen_epsilon_nfa_with_flags(ast, config, flags, copies)?
+            .to_dfa()
+            .complement()
+            .minimized_dfa()
+            .to_enfa(),
+        AstKind::Star(ast) => {
+            let mut nfa = gen_epsilon_nfa_with_flags(ast, config, flags, copies)?;
+            star_nfa(&mut nfa);
+            nfa
+        }
+        AstKind::Plus(ast) => {
+            let mut nfa = gen_epsilon_nfa_with_flags(ast, config, flags, copies)?;
+            plus_nfa(&mut nfa);
+            nfa
+        }
+        AstKind::Optional(ast) => {
+            let mut nfa = gen_epsilon_nfa_with_flags(ast, config, flags, copies)?;
+            optional_nfa(&mut nfa);
+            nfa
+        }
+        AstKind::Repeat {
+            ast: operand,
+            min,
+            max,
+        } => {
+            let limit = config.get_repetition_limit();
+            // The operand is built at least once, even for `{0}`.
+            let count = copies.saturating_mul(max.unwrap_or(*min).max(1));
+            if count > limit {
+                // Point at just the `{...}` following the operand.
+                return Err(RegexError::new(
+                    RegexErrorKind::Repetition(RepetitionError::ExceedsLimit { count, limit }),
+                    Span::new(operand.span.end, ast.span.end),
+                ));
+            }
+            repeat_nfa(
+                gen_epsilon_nfa_with_flags(operand, config, flags, count)?,
+                *min,
+                *max,
+            )
+        }
+    };
+    Ok(nfa)
 }
 
 #[wasm_bindgen]
@@ -297,7 +574,147 @@ impl FaRep {
 }
 
 pub type NfaIx = u32;
-pub type NfaGraph = DiGraphMap<NfaIx, BTreeSet<char>>;
+pub type NfaGraph = DiGraphMap<NfaIx, CharSet>;
+
+/// Adds the chars in `set` to the transition from `u` to `v`, creating the
+/// edge if needed.
+fn add_nfa_edge(graph: &mut NfaGraph, u: NfaIx, v: NfaIx, set: &CharSet) {
+    if let Some(transition_chars) = graph.edge_weight_mut(u, v) {
+        *transition_chars = transition_chars.union(set);
+    } else {
+        graph.add_edge(u, v, set.clone());
+    }
+}
+
+/// A deterministic automaton with a transition out of every state for every
+/// char, built with `Nfa::to_dfa` or `Nfa::determinize`.
+///
+/// Chars every state treats alike are grouped into classes, and the
+/// transitions are a dense table with a row for each state and a column for
+/// each class. Reading a char then takes looking up its class and a single
+/// entry of the table. Where the automaton it was built from had no
+/// transition, the table leads to an explicit dead state, which rejects and
+/// never leaves.
+#[derive(Debug, Clone)]
+#[wasm_bindgen]
+pub struct Dfa {
+    /// The chars in each class. Class 0 holds the chars of the alphabet no
+    /// state has a transition over, and may be empty.
+    classes: Vec<CharSet>,
+    /// The class of every char. Chars outside of the alphabet are in class 0.
+    class_map: ClassMap,
+    /// The next state for each state and class, a row of classes at a time.
+    table: Vec<NfaIx>,
+    start: NfaIx,
+    dead: NfaIx,
+    fin: Vec<bool>,
+    alphabet: CharSet,
+}
+
+#[wasm_bindgen]
+impl Dfa {
+    pub fn start(&self) -> NfaIx {
+        self.start
+    }
+
+    /// Returns the dead state, the last one.
+    pub fn dead_state(&self) -> NfaIx {
+        self.dead
+    }
+
+    /// Returns the number of states, including the dead state.
+    pub fn state_count(&self) -> usize {
+        self.fin.len()
+    }
+
+    /// Returns the state reached from `state` by reading `c`.
+    pub fn next_state(&self, state: NfaIx, c: char) -> NfaIx {
+        let row = usize::try_from(state).unwrap() * self.classes.len();
+        self.table[row + self.class_map.get(c)]
+    }
+
+    pub fn is_final(&self, state: NfaIx) -> bool {
+        self.fin[usize::try_from(state).unwrap()]
+    }
+
+    /// Returns whether `state` is the dead state, from which nothing is
+    /// accepted anymore.
+    pub fn is_dead(&self, state: NfaIx) -> bool {
+        state == self.dead
+    }
+
+    /// Returns whether the whole of `text` is accepted.
+    pub fn is_match(&self, text: &str) -> bool {
+        let mut state = self.start;
+        for c in text.chars() {
+            state = self.next_state(state, c);
+            if self.is_dead(state) {
+                return false;
+            }
+        }
+        self.is_final(state)
+    }
+
+    /// Returns whether the whole of `bytes` is accepted by a DFA reading
+    /// bytes, like one built from an ε-NFA from `ENfa::to_utf8`. The bytes
+    /// don't have to be valid UTF-8.
+    pub fn is_match_bytes(&self, bytes: &[u8]) -> bool {
+        let mut state = self.start;
+        for &b in bytes {
+            state = self.next_state(state, char::from(b));
+            if self.is_dead(state) {
+                return false;
+            }
+        }
+        self.is_final(state)
+    }
+
+    /// Returns the automaton for drawing, with each edge labelled by every
+    /// char it is taken on. With `hide_dead`, the dead state and the edges
+    /// into it are left out, as in the NFAs the DFA is built from.
+    pub fn to_fa_rep(&self, hide_dead: bool) -> FaRep {
+        let states = (0..NfaIx::try_from(self.state_count()).unwrap())
+            .filter(|&u| !(hide_dead && self.is_dead(u)));
+        let mut graph = NfaGraph::new();
+        for u in states.clone() {
+            graph.add_node(u);
+        }
+        for u in states {
+            let row = usize::try_from(u).unwrap() * self.classes.len();
+            for (set, &v) in self.classes.iter().zip(&self.table[row..]) {
+                if !(set.is_empty() || hide_dead && self.is_dead(v)) {
+                    add_nfa_edge(&mut graph, u, v, set);
+                }
+            }
+        }
+        let fin = (0..self.fin.len())
+            .filter(|&v| self.fin[v])
+            .map(|v| NfaIx::try_from(v).unwrap())
+            .collect();
+        Nfa {
+            graph,
+            start: self.start,
+            fin,
+            alphabet: self.alphabet.clone(),
+        }
+        .to_fa_rep()
+    }
+}
+
+/// The algorithms `Nfa::minimized_dfa_with` can minimize an automaton with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[wasm_bindgen]
+pub enum Minimizer {
+    /// Brzozowski's algorithm, determinizing the reverse of the automaton
+    /// twice. It works on NFAs directly, but the first determinization can
+    /// blow up exponentially, even when the result is small.
+    #[default]
+    Brzozowski,
+    /// Hopcroft's partition refinement, on the automaton determinized once
+    /// with the subset construction. The refinement takes O(n log n) steps for
+    /// each class of chars, for a DFA with n states.
+    Hopcroft,
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[wasm_bindgen]
@@ -305,6 +722,11 @@ pub struct Nfa {
     graph: NfaGraph,
     start: NfaIx,
     fin: Vec<NfaIx>,
+    /// Every char the automaton reads from. Transitions are always labelled
+    /// with subsets of it, and operations like complementing a class or an
+    /// automaton are taken relative to it. Automata reading bytes have every
+    /// byte as their alphabet, as in `CharSet::all_bytes`.
+    alphabet: CharSet,
 }
 
 #[wasm_bindgen]
@@ -316,25 +738,344 @@ impl ENfa {
             fin: self.fin.clone(),
         }
     }
+
+    /// Returns whether the whole of `text` is accepted, by running the ε-NFA
+    /// on it directly while keeping track of every state it could be in.
+    pub fn simulate(&self, text: &str) -> bool {
+        let mut chars = text.chars().peekable();
+        let mut states =
+            self.epsilon_closure(BTreeSet::from([self.start]), None, chars.peek().copied());
+        while let Some(c) = chars.next() {
+            let mut next_states = BTreeSet::new();
+            for &u in &states {
+                for (_, v, edges) in self.graph.edges(u) {
+                    if edges.iter().any(|edge| edge.matches(c)) {
+                        next_states.insert(v);
+                    }
+                }
+            }
+            states = self.epsilon_closure(next_states, Some(c), chars.peek().copied());
+            if states.is_empty() {
+                return false;
+            }
+        }
+        states.iter().any(|v| self.fin.contains(v))
+    }
+}
+
+impl ENfaEdge {
+    /// Returns whether the edge can be taken without reading a char or
+    /// checking an assertion. Tags only matter for capturing.
+    fn is_epsilon(&self) -> bool {
+        matches!(self, ENfaEdge::Epsilon | ENfaEdge::Tag(_))
+    }
+
+    /// Returns whether the edge can be taken by reading `c`.
+    fn matches(&self, c: char) -> bool {
+        match self {
+            ENfaEdge::Char(d) => *d == c,
+            ENfaEdge::Class(set) => set.contains(c),
+            ENfaEdge::Bytes(lo, hi) => (char::from(*lo)..=char::from(*hi)).contains(&c),
+            ENfaEdge::Epsilon | ENfaEdge::Look(_) | ENfaEdge::Tag(_) => false,
+        }
+    }
+}
+
+/// The capture slots of a thread of the Pike VM, each holding a byte offset
+/// once set.
+pub type Slots = Vec<Option<usize>>;
+
+/// Returns whether the captures in `a` are preferred over the ones in `b`.
+///
+/// Groups are compared in order, and the first one telling the two apart
+/// decides: a group taking part in the match wins over one that doesn't, then
+/// the earlier start wins, and then the later end. This is the POSIX rule of
+/// each group matching leftmost-longest, after the groups before it have.
+fn prefer(a: &[Option<usize>], b: &[Option<usize>]) -> bool {
+    let key = |group: &[Option<usize>]| (group[0].map(Reverse), group[1]);
+    for (a, b) in a.chunks(2).zip(b.chunks(2)) {
+        match key(a).cmp(&key(b)) {
+            Ordering::Greater => return true,
+            Ordering::Less => return false,
+            Ordering::Equal => {}
+        }
+    }
+    false
+}
+
+impl ENfa {
+    /// Runs the ε-NFA over `haystack[start..end]` as a Pike VM, and returns
+    /// the capture slots of the preferred way of accepting it, or `None` if
+    /// it isn't accepted. Anchors are checked against the whole haystack.
+    /// There are slots for as many groups as `nested` has entries, where
+    /// `nested[i]` is one past the last group nested inside group `i`, as in
+    /// `Ast::nested_captures`.
+    ///
+    /// A thread is kept for each state the automaton could be in, along with
+    /// the slots set on the way there. When two threads meet in a state, only
+    /// the one `prefer` picks lives on, as whatever follows applies to both
+    /// alike. This keeps the run linear in the length of the text.
+    pub fn capture_slots(
+        &self,
+        haystack: &str,
+        start: usize,
+        end: usize,
+        nested: &[usize],
+    ) -> Option<Slots> {
+        let char_at = |pos: usize| haystack[pos..].chars().next();
+        let prev = haystack[..start].chars().next_back();
+        let mut threads = BTreeMap::new();
+        let slots = vec![None; 2 * nested.len()];
+        let at = (start, prev, char_at(start));
+        self.add_thread(&mut threads, self.start, slots, nested, at);
+
+        for (offset, c) in haystack[start..end].char_indices() {
+            let pos = start + offset + c.len_utf8();
+            let mut next_threads = BTreeMap::new();
+            for (&u, slots) in &threads {
+                for (_, v, edges) in self.graph.edges(u) {
+                    if edges.iter().any(|edge| edge.matches(c)) {
+                        let at = (pos, Some(c), char_at(pos));
+                        self.add_thread(&mut next_threads, v, slots.clone(), nested, at);
+                    }
+                }
+            }
+            threads = next_threads;
+            if threads.is_empty() {
+                return None;
+            }
+        }
+
+        threads
+            .into_iter()
+            .filter(|(v, _)| self.fin.contains(v))
+            .map(|(_, slots)| slots)
+            .reduce(|best, slots| if prefer(&slots, &best) { slots } else { best })
+    }
+
+    /// Adds a thread in state `u` with the given `slots`, along with every
+    /// thread following from it without reading a char. `at` holds the byte
+    /// offset and the chars before and after it.
+    ///
+    /// Opening a group again, in a later iteration of a repetition, clears
+    /// the groups nested inside of it, which only report what they matched in
+    /// the same iteration.
+    fn add_thread(
+        &self,
+        threads: &mut BTreeMap<u32, Slots>,
+        u: u32,
+        slots: Slots,
+        nested: &[usize],
+        (pos, prev, next): (usize, Option<char>, Option<char>),
+    ) {
+        let mut stack = vec![(u, slots)];
+        while let Some((u, slots)) = stack.pop() {
+            if threads.get(&u).is_some_and(|old| !prefer(&slots, old)) {
+                continue;
+            }
+            for (_, v, edges) in self.graph.edges(u) {
+                for edge in edges {
+                    match edge {
+                        ENfaEdge::Epsilon => stack.push((v, slots.clone())),
+                        ENfaEdge::Tag(slot) => {
+                            let slot = usize::try_from(*slot).unwrap();
+                            let mut slots = slots.clone();
+                            if slot % 2 == 0 {
+                                let group = slot / 2;
+                                slots[slot + 2..2 * nested[group]].fill(None);
+                            }
+                            slots[slot] = Some(pos);
+                            stack.push((v, slots));
+                        }
+                        ENfaEdge::Look(look) if look.holds(prev, next) => {
+                            stack.push((v, slots.clone()))
+                        }
+                        ENfaEdge::Look(_)
+                        | ENfaEdge::Char(_)
+                        | ENfaEdge::Class(_)
+                        | ENfaEdge::Bytes(..) => {}
+                    }
+                }
+            }
+            threads.insert(u, slots);
+        }
+    }
+
+    /// Returns the states reachable from `states` without reading a char, at
+    /// a position between the chars `prev` and `next`.
+    fn epsilon_closure(
+        &self,
+        states: BTreeSet<u32>,
+        prev: Option<char>,
+        next: Option<char>,
+    ) -> BTreeSet<u32> {
+        let mut stack: Vec<_> = states.into_iter().collect();
+        let mut closure = BTreeSet::new();
+        while let Some(u) = stack.pop() {
+            if !closure.insert(u) {
+                continue;
+            }
+            for (_, v, edges) in self.graph.edges(u) {
+                let passable = edges.iter().any(|edge| match edge {
+                    ENfaEdge::Epsilon | ENfaEdge::Tag(_) => true,
+                    ENfaEdge::Look(look) => look.holds(prev, next),
+                    ENfaEdge::Char(_) | ENfaEdge::Class(_) | ENfaEdge::Bytes(..) => false,
+                });
+                if passable {
+                    stack.push(v);
+                }
+            }
+        }
+        closure
+    }
 }
 
 impl ENfa {
+    /// Returns an ε-NFA accepting every string with a match of this one
+    /// somewhere inside it, which is what searching a larger text for the
+    /// pattern means.
+    ///
+    /// This wraps the automaton in implicit `[^]*` loops on both sides.
+    /// Anchors keep their meaning, since they are only ever checked against
+    /// the whole text: the loop in front of `^a` can only be taken if it reads
+    /// nothing, or in multi-line mode if it stops right after a `\n`.
+    pub fn unanchored(self) -> ENfa {
+        concat_nfa(concat_nfa(gen_any_string_nfa(), self), gen_any_string_nfa())
+    }
+
+    /// Returns whether the automaton has any assertions, like `^` or `$`.
+    pub fn has_looks(&self) -> bool {
+        self.graph
+            .all_edges()
+            .any(|(_, _, w)| w.iter().any(|edge| matches!(edge, ENfaEdge::Look(_))))
+    }
+
+    /// Returns the states reachable from `u` without reading a char, along
+    /// with what the next char has to be for the assertions on the way to
+    /// hold, given what came `before`.
+    fn look_closure(&self, u: u32, before: Before) -> BTreeSet<(u32, After)> {
+        let mut closure = BTreeSet::new();
+        let mut stack = vec![(u, After::Any)];
+        while let Some((v, after)) = stack.pop() {
+            if !closure.insert((v, after)) {
+                continue;
+            }
+            for (_, w, edges) in self.graph.edges(v) {
+                for edge in edges {
+                    match edge {
+                        ENfaEdge::Epsilon | ENfaEdge::Tag(_) => stack.push((w, after)),
+                        ENfaEdge::Look(look) => {
+                            if let Some(next) = look.check(before) {
+                                stack.push((w, after.max(next)));
+                            }
+                        }
+                        ENfaEdge::Char(_) | ENfaEdge::Class(_) | ENfaEdge::Bytes(..) => {}
+                    }
+                }
+            }
+        }
+        closure
+    }
+
+    /// Removes the ε and look edges of an ε-NFA with assertions in it.
+    ///
+    /// Whether an assertion holds depends on the chars around it, so each
+    /// state is split by what the char before it was, and the transitions
+    /// out of a closure are narrowed down to the chars the assertions passed
+    /// in it allow next. A closure reaching a final state accepts, as every
+    /// assertion holds at the end of the text.
+    fn to_nfa_with_looks(&self) -> (Nfa, Vec<BTreeSet<u32>>) {
+        let new_line = CharSet::from_char('\n');
+        let mut state_to_id = BTreeMap::new();
+        let mut graph = NfaGraph::new();
+        let mut accepts = vec![BTreeSet::new()];
+        let mut que = VecDeque::new();
+        state_to_id.insert((self.start, Before::TextStart), 0);
+        graph.add_node(0);
+        que.push_back((self.start, Before::TextStart));
+
+        while let Some((u, before)) = que.pop_front() {
+            let id = state_to_id[&(u, before)];
+            for (v, after) in self.look_closure(u, before) {
+                if self.fin.contains(&v) {
+                    accepts[usize::try_from(id).unwrap()].insert(v);
+                }
+                let allowed = match after {
+                    After::Any => CharSet::full(),
+                    After::NewLineOrEnd => new_line.clone(),
+                    After::End => continue,
+                };
+
+                for (_, w, edges) in self.graph.edges(v) {
+                    for edge in edges {
+                        let set = match edge {
+                            ENfaEdge::Char(c) => CharSet::from_char(*c),
+                            ENfaEdge::Class(set) => set.clone(),
+                            ENfaEdge::Bytes(lo, hi) => CharSet::from_bytes(*lo, *hi),
+                            ENfaEdge::Epsilon | ENfaEdge::Look(_) | ENfaEdge::Tag(_) => continue,
+                        }
+                        .intersection(&allowed);
+                        let splits = [
+                            (set.intersection(&new_line), Before::NewLine),
+                            (set.difference(&new_line), Before::Other),
+                        ];
+                        for (set, next_before) in splits {
+                            if set.is_empty() {
+                                continue;
+                            }
+                            let next_id = match state_to_id.get(&(w, next_before)) {
+                                Some(&next_id) => next_id,
+                                None => {
+                                    let next_id = u32::try_from(state_to_id.len()).unwrap();
+                                    state_to_id.insert((w, next_before), next_id);
+                                    graph.add_node(next_id);
+                                    accepts.push(BTreeSet::new());
+                                    que.push_back((w, next_before));
+                                    next_id
+                                }
+                            };
+                            add_nfa_edge(&mut graph, id, next_id, &set);
+                        }
+                    }
+                }
+            }
+        }
+
+        let nfa = Nfa {
+            graph,
+            start: 0,
+            fin: accepting_nodes(&accepts),
+            alphabet: self.alphabet(),
+        };
+        (nfa, accepts)
+    }
+
     pub fn to_nfa(&self) -> Nfa {
+        self.to_nfa_with_accepts().0
+    }
+
+    /// Removes the ε edges like `to_nfa`, and also returns, for each state of
+    /// the NFA, the final states of the ε-NFA it accepts by reaching. This
+    /// tells apart automata joined with `union_all_nfa`.
+    pub fn to_nfa_with_accepts(&self) -> (Nfa, Vec<BTreeSet<u32>>) {
+        if self.has_looks() {
+            return self.to_nfa_with_looks();
+        }
+
         let mut epsilon_graph = DiGraphMap::new();
         for node in self.graph.nodes() {
             epsilon_graph.add_node(node);
         }
         self.graph
             .all_edges()
-            .filter(|(_, _, w)| w.contains(&ENfaEdge::Epsilon))
+            .filter(|(_, _, w)| w.iter().any(ENfaEdge::is_epsilon))
             .for_each(|(i, j, _)| {
                 epsilon_graph.add_edge(i, j, ());
             });
 
         let components = tarjan_scc(&epsilon_graph);
         let mut graph = NfaGraph::new();
-        let mut id_to_comp = Vec::with_capacity(epsilon_graph.node_count());
-        id_to_comp.resize(epsilon_graph.node_count(), 0u32);
+        let mut id_to_comp = vec![0u32; epsilon_graph.node_count()];
         for (i, component) in components.iter().enumerate() {
             graph.add_node(u32::try_from(i).unwrap());
             for v in component {
@@ -348,45 +1089,40 @@ impl ENfa {
                 id_to_comp[usize::try_from(i).unwrap()],
                 id_to_comp[usize::try_from(j).unwrap()],
             );
-            if comp1 == comp2 {
-                continue;
-            }
-
             for elem in w {
                 match elem {
                     ENfaEdge::Char(c) => {
-                        if let Some(transition_chars) = graph.edge_weight_mut(comp1, comp2) {
-                            transition_chars.insert(*c);
-                        } else {
-                            let mut transition_chars = BTreeSet::new();
-                            transition_chars.insert(*c);
-                            graph.add_edge(comp1, comp2, transition_chars);
-                        }
+                        add_nfa_edge(&mut graph, comp1, comp2, &CharSet::from_char(*c));
                     }
-                    ENfaEdge::Epsilon => {
+                    ENfaEdge::Class(set) => {
+                        add_nfa_edge(&mut graph, comp1, comp2, set);
+                    }
+                    ENfaEdge::Bytes(lo, hi) => {
+                        add_nfa_edge(&mut graph, comp1, comp2, &CharSet::from_bytes(*lo, *hi));
+                    }
+                    // Epsilon edges within a component are already accounted
+                    // for by merging it into a single node.
+                    ENfaEdge::Epsilon | ENfaEdge::Tag(_) if comp1 == comp2 => {}
+                    ENfaEdge::Epsilon | ENfaEdge::Tag(_) => {
                         comp_epsilon_graph.add_edge(comp1, comp2, ());
                     }
+                    ENfaEdge::Look(_) => unreachable!("handled by to_nfa_with_looks"),
                 }
             }
         }
 
-        let mut fin_comps = BTreeSet::new();
+        let mut accepts = vec![BTreeSet::new(); components.len()];
         for v in &self.fin {
-            fin_comps.insert(id_to_comp[usize::try_from(*v).unwrap()]);
+            accepts[usize::try_from(id_to_comp[usize::try_from(*v).unwrap()]).unwrap()].insert(*v);
         }
 
         let mut graph_copy = graph.clone();
         for u in 0..u32::try_from(components.len()).unwrap() {
             for (_, v, _) in comp_epsilon_graph.edges(u) {
-                if fin_comps.contains(&v) {
-                    fin_comps.insert(u);
-                }
+                let reached = accepts[usize::try_from(v).unwrap()].clone();
+                accepts[usize::try_from(u).unwrap()].extend(reached);
                 for (_, w, m) in graph_copy.edges(v) {
-                    if let Some(transition_chars) = graph.edge_weight_mut(u, w) {
-                        transition_chars.append(&mut m.clone());
-                    } else {
-                        graph.add_edge(u, w, m.clone());
-                    }
+                    add_nfa_edge(&mut graph, u, w, m);
                 }
             }
 
@@ -395,19 +1131,202 @@ impl ENfa {
             }
         }
 
-        Nfa {
+        let nfa = Nfa {
             graph,
             start: id_to_comp[usize::try_from(self.start).unwrap()],
-            fin: fin_comps.into_iter().collect(),
+            fin: accepting_nodes(&accepts),
+            alphabet: self.alphabet(),
+        };
+        (nfa, accepts)
+    }
+}
+
+/// Splits the `live` states into blocks of equivalent states with Hopcroft's
+/// algorithm, given their labels and the state each one leads to over each
+/// class of chars. Returns the block of each state, numbered in order of their
+/// first states, and the number of blocks.
+///
+/// Missing transitions lead to a dead state, which gets a block of its own,
+/// and the other states start out split into blocks by label. A worklist holds
+/// pairs of a block and a class to split by: every block with some states
+/// going into it over that class and some not is split in two. The smaller
+/// half is then added to the worklist with every class, which is enough, as
+/// the larger half tells apart the same states given the block they came
+/// from. So each state is only in a logarithmic number of the blocks taken
+/// off the worklist. The transitions into each state are kept by class, and a
+/// split only moves the states going into the splitter, so it takes
+/// O(n log n) steps for each class.
+fn hopcroft_partition(
+    live: &[usize],
+    labels: &[Option<usize>],
+    next: &[Vec<Option<usize>>],
+    class_count: usize,
+) -> (Vec<usize>, usize) {
+    let dead = next.len();
+    // The states leading into each state over each class, at
+    // `prev[v * class_count + class]`.
+    let mut prev = vec![vec![]; (dead + 1) * class_count];
+    for &u in live {
+        for (class, v) in next[u].iter().enumerate() {
+            prev[v.unwrap_or(dead) * class_count + class].push(u);
+        }
+    }
+    for class in 0..class_count {
+        prev[dead * class_count + class].push(dead);
+    }
+
+    let mut blocks: Vec<Vec<usize>> = vec![];
+    let mut block_of = vec![0; dead + 1];
+    // Where each state is in its block.
+    let mut index = vec![0; dead + 1];
+    let mut label_to_block = BTreeMap::new();
+    for &u in live {
+        let id = *label_to_block.entry(labels[u]).or_insert_with(|| {
+            blocks.push(vec![]);
+            blocks.len() - 1
+        });
+        block_of[u] = id;
+        index[u] = blocks[id].len();
+        blocks[id].push(u);
+    }
+    block_of[dead] = blocks.len();
+    blocks.push(vec![dead]);
+
+    let mut work: Vec<_> = (0..blocks.len())
+        .flat_map(|id| (0..class_count).map(move |class| (id, class)))
+        .collect();
+    while let Some((splitter, class)) = work.pop() {
+        let mut entering: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for &v in &blocks[splitter] {
+            for &u in &prev[v * class_count + class] {
+                entering.entry(block_of[u]).or_default().push(u);
+            }
+        }
+        for (id, inside) in entering {
+            let len = blocks[id].len();
+            if inside.len() == len {
+                continue;
+            }
+            // Move the states going into the splitter to the end of the
+            // block, and cut them off there.
+            for (i, &u) in inside.iter().enumerate() {
+                let (from, to) = (index[u], len - 1 - i);
+                let other = blocks[id][to];
+                blocks[id].swap(from, to);
+                (index[other], index[u]) = (from, to);
+            }
+            let mut outside = std::mem::take(&mut blocks[id]);
+            let inside = outside.split_off(len - inside.len());
+            for (i, &u) in inside.iter().enumerate() {
+                index[u] = i;
+            }
+            let (stay, split) = match inside.len() <= outside.len() {
+                true => (outside, inside),
+                false => (inside, outside),
+            };
+            let split_id = blocks.len();
+            for &u in &split {
+                block_of[u] = split_id;
+            }
+            blocks[id] = stay;
+            blocks.push(split);
+            work.extend((0..class_count).map(|class| (split_id, class)));
+        }
+    }
+
+    let mut renumbered = BTreeMap::new();
+    let mut block = vec![0; dead];
+    for &u in live {
+        let id = renumbered.len();
+        block[u] = *renumbered.entry(block_of[u]).or_insert(id);
+    }
+    (block, renumbered.len())
+}
+
+/// Returns the nodes accepting anything at all.
+fn accepting_nodes(accepts: &[BTreeSet<u32>]) -> Vec<NfaIx> {
+    (0..accepts.len())
+        .filter(|&u| !accepts[u].is_empty())
+        .map(|u| NfaIx::try_from(u).unwrap())
+        .collect()
+}
+
+impl ENfa {
+    /// Returns what the automaton reads: bytes if it has any transitions over
+    /// bytes, and chars otherwise.
+    fn alphabet(&self) -> CharSet {
+        let reads_bytes = self
+            .graph
+            .all_edges()
+            .any(|(_, _, w)| w.iter().any(|edge| matches!(edge, ENfaEdge::Bytes(..))));
+        match reads_bytes {
+            true => CharSet::all_bytes(),
+            false => CharSet::full(),
+        }
+    }
+
+    /// Returns an ε-NFA accepting the UTF-8 encodings of the strings this one
+    /// accepts, reading them a byte at a time.
+    ///
+    /// Each transition over a set of chars is replaced by the byte range
+    /// sequences from `utf8_sequences`, sharing their common prefixes in a
+    /// trie. Other transitions are kept, so `^` and `$` keep working, with
+    /// `\n` being the same as its byte.
+    pub fn to_utf8(&self) -> ENfa {
+        let mut graph = ENfaGraph::new();
+        for v in self.graph.nodes() {
+            graph.add_node(v);
+        }
+        let mut enfa = ENfa {
+            graph,
+            start: self.start,
+            fin: self.fin.clone(),
+        };
+
+        for (u, v, edges) in self.graph.all_edges() {
+            for edge in edges {
+                let set = match edge {
+                    ENfaEdge::Char(c) => CharSet::from_char(*c),
+                    ENfaEdge::Class(set) => set.clone(),
+                    edge => {
+                        enfa.add_edge(u, v, edge.clone());
+                        continue;
+                    }
+                };
+                let mut trie = BTreeMap::new();
+                for sequence in utf8_sequences(&set) {
+                    let mut node = u;
+                    for (i, &(lo, hi)) in sequence.iter().enumerate() {
+                        let next = match i + 1 == sequence.len() {
+                            true => v,
+                            false => *trie.entry((node, lo, hi)).or_insert_with(|| {
+                                let next = u32::try_from(enfa.graph.node_count()).unwrap();
+                                enfa.graph.add_node(next)
+                            }),
+                        };
+                        enfa.add_edge(node, next, ENfaEdge::Bytes(lo, hi));
+                        node = next;
+                    }
+                }
+            }
         }
+        enfa
+    }
+
+    /// Returns the minimal DFA accepting the same language.
+    fn to_dfa(&self) -> Nfa {
+        self.to_nfa().minimized_dfa()
     }
 }
 
-fn merge_char_u32map_maps(a: &mut BTreeMap<char, BTreeSet<u32>>, b: BTreeMap<char, BTreeSet<u32>>) {
-    for (c, b_ids) in b {
-        match a.get_mut(&c) {
+fn merge_class_u32map_maps(
+    a: &mut BTreeMap<usize, BTreeSet<u32>>,
+    b: BTreeMap<usize, BTreeSet<u32>>,
+) {
+    for (class, b_ids) in b {
+        match a.get_mut(&class) {
             None => {
-                a.insert(c, b_ids);
+                a.insert(class, b_ids);
             }
             Some(a_ids) => {
                 for b_id in b_ids {
@@ -421,15 +1340,124 @@ fn merge_char_u32map_maps(a: &mut BTreeMap<char, BTreeSet<u32>>, b: BTreeMap<cha
 #[wasm_bindgen]
 impl Nfa {
     pub fn to_fa_rep(&self) -> FaRep {
+        let dot_str = match self.reads_bytes() {
+            false => format!("{:?}", Dot::new(&self.graph)),
+            true => {
+                let mut graph = DiGraphMap::new();
+                for v in self.graph.nodes() {
+                    graph.add_node(v);
+                }
+                for (u, v, set) in self.graph.all_edges() {
+                    graph.add_edge(u, v, ByteClass(set));
+                }
+                format!("{:?}", Dot::new(&graph))
+            }
+        };
         FaRep {
-            dot_str: format!("{:?}", Dot::new(&self.graph)),
+            dot_str,
             start: self.start,
             fin: self.fin.clone(),
         }
     }
+
+    /// Returns whether the whole of `text` is accepted, by laying the DFA out
+    /// as a `Dfa` table with `to_dfa` and walking it.
+    ///
+    /// The automaton has to be deterministic, like the ones returned by
+    /// `minimized_dfa` and `subset_construction`. Any other automaton should
+    /// be run with `simulate` instead. The table is built on every call, so
+    /// to run the same DFA on many texts, keep the `Dfa` from `to_dfa`.
+    pub fn is_match(&self, text: &str) -> bool {
+        self.to_dfa().is_match(text)
+    }
+
+    /// Returns whether the whole of `bytes` is accepted by a DFA reading
+    /// bytes, like the minimal DFA of an ε-NFA from `ENfa::to_utf8`. The bytes
+    /// don't have to be valid UTF-8.
+    pub fn is_match_bytes(&self, bytes: &[u8]) -> bool {
+        self.to_dfa().is_match_bytes(bytes)
+    }
+
+    /// Returns whether the whole of `text` is accepted, by running the NFA on
+    /// it while keeping track of every state it could be in.
+    pub fn simulate(&self, text: &str) -> bool {
+        let mut states = BTreeSet::from([self.start]);
+        for c in text.chars() {
+            states = states
+                .iter()
+                .flat_map(|&u| self.graph.edges(u))
+                .filter(|(_, _, set)| set.contains(c))
+                .map(|(_, v, _)| v)
+                .collect();
+            if states.is_empty() {
+                return false;
+            }
+        }
+        states.iter().any(|v| self.fin.contains(v))
+    }
 }
 
 impl Nfa {
+    pub fn alphabet(&self) -> &CharSet {
+        &self.alphabet
+    }
+
+    pub fn state_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    /// Returns whether the automaton reads bytes rather than chars, as in
+    /// `CharSet::from_bytes`.
+    pub fn reads_bytes(&self) -> bool {
+        self.alphabet == CharSet::all_bytes()
+    }
+
+    /// Lays the DFA out as a `Dfa`, completing it with a dead state. The
+    /// automaton has to be deterministic, and its states keep their numbers.
+    /// Use `determinize` for any other automaton.
+    pub fn to_dfa(&self) -> Dfa {
+        let edges: Vec<_> = self.graph.all_edges().collect();
+        let (classes, edge_classes) =
+            split_classes(&edges.iter().map(|(_, _, set)| *set).collect::<Vec<_>>());
+        let covered = classes
+            .iter()
+            .fold(CharSet::new(), |covered, set| covered.union(set));
+        // Class 0 is for the chars without any transitions.
+        let classes: Vec<_> = std::iter::once(self.alphabet.difference(&covered))
+            .chain(classes)
+            .collect();
+
+        let node_count = self.graph.node_count();
+        let dead = NfaIx::try_from(node_count).unwrap();
+        let mut table = vec![dead; (node_count + 1) * classes.len()];
+        for ((u, v, _), edge_classes) in edges.iter().zip(edge_classes) {
+            let row = usize::try_from(*u).unwrap() * classes.len();
+            for class in edge_classes {
+                table[row + class + 1] = *v;
+            }
+        }
+
+        let mut fin = vec![false; node_count + 1];
+        for v in &self.fin {
+            fin[usize::try_from(*v).unwrap()] = true;
+        }
+        Dfa {
+            class_map: ClassMap::new(&classes, 0),
+            classes,
+            table,
+            start: self.start,
+            dead,
+            fin,
+            alphabet: self.alphabet.clone(),
+        }
+    }
+
+    /// Determinizes the automaton with the subset construction, into a
+    /// `Dfa`.
+    pub fn determinize(self) -> Dfa {
+        self.subset_construction().to_dfa()
+    }
+
     pub fn remove_unreachable_nodes(&mut self) {
         let mut reachable_nodes = BTreeSet::new();
         let mut dfs = Dfs::new(&self.graph, self.start);
@@ -437,8 +1465,7 @@ impl Nfa {
             reachable_nodes.insert(v);
         }
 
-        let mut reachable_map = vec![];
-        reachable_map.resize(self.graph.node_count(), 0);
+        let mut reachable_map = vec![0; self.graph.node_count()];
 
         for v in 0..u32::try_from(self.graph.node_count()).unwrap() {
             if !reachable_nodes.contains(&v) {
@@ -473,7 +1500,7 @@ impl Nfa {
             .collect();
     }
 
-    pub fn reverse(self) -> Self {
+    fn reversed_graph(&self) -> NfaGraph {
         let mut graph = NfaGraph::new();
         for v in self.graph.nodes() {
             graph.add_node(v);
@@ -481,8 +1508,18 @@ impl Nfa {
         for (u, v, w) in self.graph.all_edges() {
             graph.add_edge(v, u, w.clone());
         }
+        graph
+    }
+
+    /// Returns an automaton accepting the reverse of every accepted string.
+    ///
+    /// Unless there is exactly one final state, this adds a new start node
+    /// standing in for all of the old final states. With no final states at
+    /// all, that node has no transitions, and nothing is accepted.
+    pub fn reverse(self) -> Self {
+        let mut graph = self.reversed_graph();
 
-        if self.fin.len() > 1 {
+        if self.fin.len() != 1 {
             let graph_copy = graph.clone();
             let new_node: u32 = u32::try_from(graph.node_count()).unwrap();
             graph.add_node(new_node);
@@ -493,13 +1530,7 @@ impl Nfa {
             }
             for u in self.fin.iter() {
                 for (_, v, w) in graph_copy.edges(*u) {
-                    if let Some(transition_chars) = graph.edge_weight_mut(new_node, v) {
-                        for new_char in w {
-                            transition_chars.insert(*new_char);
-                        }
-                    } else {
-                        graph.add_edge(new_node, v, w.clone());
-                    }
+                    add_nfa_edge(&mut graph, new_node, v, w);
                 }
             }
 
@@ -507,40 +1538,78 @@ impl Nfa {
                 graph,
                 start: new_node,
                 fin,
+                alphabet: self.alphabet,
             }
         } else {
             Nfa {
                 graph,
                 start: self.fin[0],
                 fin: vec![self.start],
+                alphabet: self.alphabet,
             }
         }
     }
 
+    /// Returns an automaton accepting every prefix of an accepted string.
+    ///
+    /// Every state some final state can be reached from becomes final. The
+    /// other states are left as they are, as nothing is accepted through
+    /// them.
+    pub fn prefixes(self) -> Self {
+        let reversed = self.reversed_graph();
+        let mut live: BTreeSet<NfaIx> = self.fin.iter().copied().collect();
+        let mut stack = self.fin.clone();
+        while let Some(v) = stack.pop() {
+            for (_, u, _) in reversed.edges(v) {
+                if live.insert(u) {
+                    stack.push(u);
+                }
+            }
+        }
+        Nfa {
+            fin: live.into_iter().collect(),
+            ..self
+        }
+    }
+
     pub fn subset_construction(self) -> Self {
+        self.subset_construction_with_subsets().0
+    }
+
+    /// Runs the subset construction, and also returns the set of states of
+    /// the NFA each state of the DFA stands for.
+    pub fn subset_construction_with_subsets(self) -> (Self, Vec<BTreeSet<NfaIx>>) {
+        let initial_state = BTreeSet::from([self.start]);
+        self.subset_construction_from(initial_state)
+    }
+
+    /// Runs the subset construction from the given set of initial states,
+    /// ignoring `self.start`.
+    fn subset_construction_from(
+        self,
+        initial_state: BTreeSet<NfaIx>,
+    ) -> (Self, Vec<BTreeSet<NfaIx>>) {
+        // Work over equivalence classes of chars rather than chars, so that
+        // wide transitions cost no more than narrow ones.
+        let edges: Vec<_> = self.graph.all_edges().collect();
+        let (classes, edge_classes) =
+            split_classes(&edges.iter().map(|(_, _, set)| *set).collect::<Vec<_>>());
+
         let mut subset_to_id: BTreeMap<BTreeSet<u32>, usize> = BTreeMap::new();
-        let mut id_to_next: Vec<BTreeMap<char, BTreeSet<u32>>> = vec![];
-        let mut singular_next: Vec<BTreeMap<char, BTreeSet<u32>>> = vec![];
-        singular_next.resize_with(self.graph.node_count(), || BTreeMap::new());
-
-        for (u, v, map) in self.graph.all_edges() {
-            let u = usize::try_from(u).unwrap();
-            for c in map {
-                if let Some(singular_subset) = singular_next[u].get_mut(c) {
-                    singular_subset.insert(v);
-                } else {
-                    let mut singular_subset = BTreeSet::new();
-                    singular_subset.insert(v);
-                    singular_next[u].insert(*c, singular_subset);
-                }
+        let mut id_to_next: Vec<BTreeMap<usize, BTreeSet<u32>>> = vec![];
+        let mut singular_next: Vec<BTreeMap<usize, BTreeSet<u32>>> = vec![];
+        singular_next.resize_with(self.graph.node_count(), BTreeMap::new);
+
+        for ((u, v, _), edge_classes) in edges.iter().zip(edge_classes) {
+            let u = usize::try_from(*u).unwrap();
+            for class in edge_classes {
+                singular_next[u].entry(class).or_default().insert(*v);
             }
         }
 
         let mut fin = vec![];
 
         let mut que = VecDeque::new();
-        let mut initial_state = BTreeSet::new();
-        initial_state.insert(self.start);
         que.push_back(initial_state);
         while !que.is_empty() {
             let subset = que.pop_front().unwrap();
@@ -550,12 +1619,12 @@ impl Nfa {
 
             let mut moves = BTreeMap::new();
             for neigh_id in &subset {
-                merge_char_u32map_maps(
+                merge_class_u32map_maps(
                     &mut moves,
                     singular_next[usize::try_from(*neigh_id).unwrap()].clone(),
                 )
             }
-            for (_, next_subset) in &moves {
+            for next_subset in moves.values() {
                 que.push_back(next_subset.clone());
             }
 
@@ -575,14 +1644,227 @@ impl Nfa {
         for (id, next_subsets) in id_to_next.iter().enumerate() {
             let id = u32::try_from(id).unwrap();
             graph.add_node(id);
-            for (c, next_subset) in next_subsets {
+            for (class, next_subset) in next_subsets {
                 let next_id = u32::try_from(subset_to_id[next_subset]).unwrap();
-                if let Some(transition_chars) = graph.edge_weight_mut(id, next_id) {
-                    transition_chars.insert(*c);
-                } else {
-                    let mut transition_chars = BTreeSet::new();
-                    transition_chars.insert(*c);
-                    graph.add_edge(id, next_id, transition_chars);
+                add_nfa_edge(&mut graph, id, next_id, &classes[*class]);
+            }
+        }
+
+        let mut subsets = vec![BTreeSet::new(); subset_to_id.len()];
+        for (subset, id) in subset_to_id {
+            subsets[id] = subset;
+        }
+        let dfa = Nfa {
+            graph,
+            start: 0,
+            fin,
+            alphabet: self.alphabet,
+        };
+        (dfa, subsets)
+    }
+
+    /// Determinizes the reverse of the automaton.
+    ///
+    /// Unlike `reverse().subset_construction()`, the subset construction starts
+    /// from the set of old final states itself rather than from a new node
+    /// standing in for them. Brzozowski's algorithm relies on this to produce
+    /// a minimal DFA.
+    fn reverse_determinize(self) -> Self {
+        let initial_state = self.fin.iter().copied().collect();
+        Nfa {
+            graph: self.reversed_graph(),
+            start: self.start,
+            fin: vec![self.start],
+            alphabet: self.alphabet,
+        }
+        .subset_construction_from(initial_state)
+        .0
+    }
+
+    /// Returns the minimal DFA accepting the same language, with
+    /// Brzozowski's algorithm.
+    pub fn minimized_dfa(self) -> Self {
+        self.minimized_dfa_with(Minimizer::Brzozowski)
+    }
+
+    /// Returns the minimal DFA accepting the same language, built with the
+    /// given algorithm. Both give the same automaton, up to the numbering of
+    /// its states.
+    pub fn minimized_dfa_with(self, minimizer: Minimizer) -> Self {
+        match minimizer {
+            Minimizer::Brzozowski => self.reverse_determinize().reverse_determinize(),
+            Minimizer::Hopcroft => {
+                let dfa = self.subset_construction();
+                let labels: Vec<_> = (0..dfa.graph.node_count())
+                    .map(|u| dfa.fin.contains(&NfaIx::try_from(u).unwrap()).then_some(0))
+                    .collect();
+                dfa.minimized_dfa_by_label(&labels).0
+            }
+        }
+    }
+
+    /// Returns the minimal DFA keeping the labels of the states apart, along
+    /// with the label of each of its states. The automaton has to be
+    /// deterministic.
+    ///
+    /// Each state has a label telling how it accepts, or `None` if it
+    /// doesn't, and the result accepts in a state with that label. This is
+    /// what a lexer needs, with final states labelled by the rule they accept
+    /// for. Brzozowski's algorithm can only tell final states from the rest,
+    /// so this refines partitions instead, with `hopcroft_partition`.
+    pub fn minimized_dfa_by_label(self, labels: &[Option<usize>]) -> (Self, Vec<Option<usize>>) {
+        let node_count = self.graph.node_count();
+        let search = |graph: &NfaGraph, roots: Vec<NfaIx>| {
+            let mut seen = vec![false; node_count];
+            let mut stack = roots;
+            while let Some(u) = stack.pop() {
+                if !std::mem::replace(&mut seen[usize::try_from(u).unwrap()], true) {
+                    stack.extend(graph.neighbors(u));
+                }
+            }
+            seen
+        };
+        // States that can't be reached, or can't reach a label, are dropped
+        // like the dead state they are equivalent to.
+        let reachable = search(&self.graph, vec![self.start]);
+        let labelled = (0..node_count).filter(|&u| labels[u].is_some());
+        let coreachable = search(
+            &self.reversed_graph(),
+            labelled.map(|u| NfaIx::try_from(u).unwrap()).collect(),
+        );
+        let live: Vec<_> = (0..node_count)
+            .filter(|&u| reachable[u] && coreachable[u])
+            .collect();
+
+        let edges: Vec<_> = self
+            .graph
+            .all_edges()
+            .filter(|&(u, v, _)| {
+                let (u, v) = (usize::try_from(u).unwrap(), usize::try_from(v).unwrap());
+                reachable[u] && coreachable[u] && reachable[v] && coreachable[v]
+            })
+            .collect();
+        let (classes, edge_classes) =
+            split_classes(&edges.iter().map(|(_, _, set)| *set).collect::<Vec<_>>());
+        let mut next = vec![vec![None; classes.len()]; node_count];
+        for ((u, v, _), edge_classes) in edges.iter().zip(edge_classes) {
+            for class in edge_classes {
+                next[usize::try_from(*u).unwrap()][class] = Some(usize::try_from(*v).unwrap());
+            }
+        }
+
+        let (block, block_count) = hopcroft_partition(&live, labels, &next, classes.len());
+
+        let mut graph = NfaGraph::new();
+        let mut block_labels = vec![None; block_count];
+        for id in 0..block_count {
+            graph.add_node(NfaIx::try_from(id).unwrap());
+        }
+        for &u in &live {
+            block_labels[block[u]] = labels[u];
+            for (class, v) in next[u].iter().enumerate() {
+                if let Some(v) = v {
+                    let (u, v) = (block[u], block[*v]);
+                    let (u, v) = (NfaIx::try_from(u).unwrap(), NfaIx::try_from(v).unwrap());
+                    add_nfa_edge(&mut graph, u, v, &classes[class]);
+                }
+            }
+        }
+
+        let start = usize::try_from(self.start).unwrap();
+        let start = match reachable[start] && coreachable[start] {
+            true => NfaIx::try_from(block[start]).unwrap(),
+            // Nothing is accepted at all, leaving just a start state.
+            false => {
+                graph.add_node(0);
+                block_labels.push(None);
+                0
+            }
+        };
+        let fin = (0..block_labels.len())
+            .filter(|&id| block_labels[id].is_some())
+            .map(|id| NfaIx::try_from(id).unwrap())
+            .collect();
+        let dfa = Nfa {
+            graph,
+            start,
+            fin,
+            alphabet: self.alphabet,
+        };
+        (dfa, block_labels)
+    }
+
+    /// Returns a DFA accepting exactly the strings over the alphabet that this
+    /// automaton rejects.
+    ///
+    /// The automaton is determinized and completed with a dead state, taking
+    /// every char a state has no transition over, before its final and
+    /// non-final states are swapped.
+    pub fn complement(self) -> Self {
+        let dfa = self.subset_construction();
+        let mut graph = dfa.graph.clone();
+        let dead = u32::try_from(graph.node_count()).unwrap();
+        graph.add_node(dead);
+        for u in dfa.graph.nodes() {
+            let covered = dfa
+                .graph
+                .edges(u)
+                .fold(CharSet::new(), |covered, (_, _, set)| covered.union(set));
+            let missing = dfa.alphabet.difference(&covered);
+            if !missing.is_empty() {
+                add_nfa_edge(&mut graph, u, dead, &missing);
+            }
+        }
+        add_nfa_edge(&mut graph, dead, dead, &dfa.alphabet);
+
+        let fin = graph.nodes().filter(|v| !dfa.fin.contains(v)).collect();
+        Nfa {
+            graph,
+            start: dfa.start,
+            fin,
+            alphabet: dfa.alphabet,
+        }
+    }
+
+    /// Returns a DFA accepting the strings accepted by both automata.
+    ///
+    /// This is the product construction: both automata are determinized, and
+    /// run side by side on each char, with a state of the result for each
+    /// reachable pair of their states.
+    pub fn intersection(self, other: Nfa) -> Self {
+        let (a, b) = (self.subset_construction(), other.subset_construction());
+
+        let mut pair_to_id = BTreeMap::new();
+        let mut graph = NfaGraph::new();
+        let mut fin = vec![];
+        let mut que = VecDeque::new();
+        pair_to_id.insert((a.start, b.start), 0);
+        graph.add_node(0);
+        que.push_back((a.start, b.start));
+
+        while let Some((p, q)) = que.pop_front() {
+            let id = pair_to_id[&(p, q)];
+            if a.fin.contains(&p) && b.fin.contains(&q) {
+                fin.push(id);
+            }
+
+            for (_, next_p, set_p) in a.graph.edges(p) {
+                for (_, next_q, set_q) in b.graph.edges(q) {
+                    let set = set_p.intersection(set_q);
+                    if set.is_empty() {
+                        continue;
+                    }
+                    let next_id = match pair_to_id.get(&(next_p, next_q)) {
+                        Some(&next_id) => next_id,
+                        None => {
+                            let next_id = u32::try_from(pair_to_id.len()).unwrap();
+                            pair_to_id.insert((next_p, next_q), next_id);
+                            graph.add_node(next_id);
+                            que.push_back((next_p, next_q));
+                            next_id
+                        }
+                    };
+                    add_nfa_edge(&mut graph, id, next_id, &set);
                 }
             }
         }
@@ -591,13 +1873,42 @@ impl Nfa {
             graph,
             start: 0,
             fin,
+            alphabet: a.alphabet.intersection(&b.alphabet),
         }
     }
 
-    pub fn minimized_dfa(self) -> Self {
-        self.reverse()
-            .subset_construction()
-            .reverse()
-            .subset_construction()
+    /// Returns a DFA accepting the strings accepted by this automaton but not
+    /// by `other`.
+    pub fn difference(self, other: Nfa) -> Self {
+        self.intersection(other.complement())
+    }
+
+    /// Returns an ε-NFA with the same states and transitions, for building
+    /// on with the Thompson constructions.
+    pub fn to_enfa(&self) -> ENfa {
+        let mut graph = ENfaGraph::new();
+        for v in self.graph.nodes() {
+            graph.add_node(v);
+        }
+        let mut enfa = ENfa {
+            graph,
+            start: self.start,
+            fin: self.fin.clone(),
+        };
+        for (u, v, set) in self.graph.all_edges() {
+            if self.reads_bytes() {
+                for &(lo, hi) in set.ranges() {
+                    let byte = |c: char| u8::try_from(c).expect("bytes are at most U+FF");
+                    enfa.add_edge(u, v, ENfaEdge::Bytes(byte(lo), byte(hi)));
+                }
+                continue;
+            }
+            let edge = match set.ranges() {
+                [(lo, hi)] if lo == hi => ENfaEdge::Char(*lo),
+                _ => ENfaEdge::Class(set.clone()),
+            };
+            enfa.add_edge(u, v, edge);
+        }
+        enfa
     }
 }
@@ -0,0 +1,156 @@
+use std::{fmt, ops::Range};
+
+use super::{
+    config::Config,
+    enfa::{gen_epsilon_nfa_from_expr, union_all_dfa, Dfa},
+    parsing::{parse_regex, RegexError},
+};
+
+/// A tokenizer built from an ordered list of rules, each a token name and the
+/// regex its tokens match.
+///
+/// Scanning goes by maximal munch: each token is the longest prefix of the
+/// rest of the input any rule matches, and when several rules match it, the
+/// one listed first wins. Anchors in a rule hold at the ends of the token.
+///
+/// The rules are compiled into a single DFA, much like a `RegexSet`, whose
+/// accepting states are labelled with the rule that wins there. It is then
+/// minimized with `Nfa::minimized_dfa_by_label`, so that states accepting for
+/// different rules are never merged.
+#[derive(Debug, Clone)]
+pub struct Lexer {
+    dfa: Dfa,
+    /// The rule accepted in each state of the DFA, if any.
+    accepts: Vec<Option<usize>>,
+    names: Vec<String>,
+}
+
+impl Lexer {
+    /// Compiles the rules into a lexer. On failure, returns the index of the
+    /// first rule that didn't compile along with its errors.
+    pub fn new<I, N, P>(rules: I) -> Result<Lexer, (usize, Vec<RegexError>)>
+    where
+        I: IntoIterator<Item = (N, P)>,
+        N: Into<String>,
+        P: AsRef<str>,
+    {
+        Lexer::with_config(rules, &Config::default())
+    }
+
+    pub fn with_config<I, N, P>(
+        rules: I,
+        config: &Config,
+    ) -> Result<Lexer, (usize, Vec<RegexError>)>
+    where
+        I: IntoIterator<Item = (N, P)>,
+        N: Into<String>,
+        P: AsRef<str>,
+    {
+        let mut names = vec![];
+        let mut enfas = vec![];
+        for (i, (name, pattern)) in rules.into_iter().enumerate() {
+            let ast = parse_regex(pattern.as_ref(), config).map_err(|errs| (i, errs))?;
+            let enfa = gen_epsilon_nfa_from_expr(&ast, config).map_err(|err| (i, vec![err]))?;
+            names.push(name.into());
+            enfas.push(enfa);
+        }
+
+        let (dfa, accepts) = union_all_dfa(enfas);
+        // The rules are numbered in order, so the first one listed is the
+        // smallest.
+        let labels: Vec<_> = accepts.iter().map(|rules| rules.first().copied()).collect();
+        let (dfa, accepts) = dfa.minimized_dfa_by_label(&labels);
+
+        Ok(Lexer {
+            dfa: dfa.to_dfa(),
+            accepts,
+            names,
+        })
+    }
+
+    /// Returns the token name of each rule, in order.
+    pub fn rule_names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+
+    /// Returns the tokens of `input`, from front to back, each as its rule's
+    /// token name along with its span in bytes.
+    ///
+    /// Where no rule matches a nonempty prefix of the rest of the input, a
+    /// `LexError` is returned for the char there, and scanning goes on after
+    /// it. Rules matching the empty string never make empty tokens.
+    pub fn tokens<'l, 'h>(&'l self, input: &'h str) -> Tokens<'l, 'h> {
+        Tokens {
+            lexer: self,
+            input,
+            pos: 0,
+        }
+    }
+
+    /// Returns the winning rule and the end of the longest token starting at
+    /// byte offset `start`, if there is one.
+    fn longest_token(&self, input: &str, start: usize) -> Option<(usize, usize)> {
+        let mut state = self.dfa.start();
+        let mut token = None;
+        for (i, c) in input[start..].char_indices() {
+            state = self.dfa.next_state(state, c);
+            if self.dfa.is_dead(state) {
+                break;
+            }
+            if let Some(rule) = self.accepts[usize::try_from(state).unwrap()] {
+                token = Some((rule, start + i + c.len_utf8()));
+            }
+        }
+        token
+    }
+}
+
+/// A char of the input no rule matches, from `Tokens`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexError {
+    span: Range<usize>,
+}
+
+impl LexError {
+    /// Returns the span of the char in bytes.
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "No rule matches at {}", self.span.start)
+    }
+}
+
+impl std::error::Error for LexError {}
+
+/// An iterator over the tokens of an input, returned by `Lexer::tokens`.
+pub struct Tokens<'l, 'h> {
+    lexer: &'l Lexer,
+    input: &'h str,
+    /// The byte offset to scan the next token from.
+    pos: usize,
+}
+
+impl<'l, 'h> Iterator for Tokens<'l, 'h> {
+    type Item = Result<(&'l str, Range<usize>), LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.pos;
+        let c = self.input[start..].chars().next()?;
+        match self.lexer.longest_token(self.input, start) {
+            Some((rule, end)) => {
+                self.pos = end;
+                Some(Ok((&self.lexer.names[rule], start..end)))
+            }
+            None => {
+                self.pos += c.len_utf8();
+                Some(Err(LexError {
+                    span: start..self.pos,
+                }))
+            }
+        }
+    }
+}
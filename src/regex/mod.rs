@@ -1,59 +1,84 @@
+mod charset;
+mod config;
 mod enfa;
+mod lexer;
 mod parsing;
+mod search;
+mod set;
 mod tokens;
+mod unicode_tables;
+mod utf8;
 
-use anyhow::Context;
+pub use charset::CharSet;
+pub use config::Config;
 use enfa::{gen_epsilon_nfa_from_expr, FaRep};
+pub use enfa::{Dfa, Minimizer};
+pub use lexer::{LexError, Lexer, Tokens};
 use parsing::tokens_to_postfix;
+pub use parsing::{
+    apply_fixes, parse_regex, Anchor, Ast, AstKind, ExprUnit, Fix, FixRep, Flag, Flags, RegexError,
+    RegexErrorKind, RegexErrorRep, RegexOp, RepetitionError, Span,
+};
+pub use search::{
+    BytesMatch, BytesMatches, BytesRegex, Captures, Match, Matches, Regex, StreamMatcher,
+};
+pub use set::RegexSet;
 use tokens::RegexTokenizer;
+pub use utf8::{utf8_sequences, Utf8Sequence};
 use wasm_bindgen::prelude::*;
 
 use self::enfa::{ENfa, Nfa};
 
 #[wasm_bindgen]
 pub fn get_debug_postexpr_string(expr: &str) -> String {
-    return format!(
+    format!(
         "{:?}",
         tokens_to_postfix(&mut RegexTokenizer::from_string(expr))
-    );
+    )
 }
 
 #[wasm_bindgen]
-pub fn get_debug_graph_json(expr: &str) -> Result<FaRep, String> {
-    let expr = tokens_to_postfix(&mut RegexTokenizer::from_string(expr))
-        .context("Failed to convert to postfix");
-    if expr.is_err() {
-        return Err(expr.unwrap_err().to_string());
-    }
-    let expr = expr.unwrap();
-
-    let enfa = gen_epsilon_nfa_from_expr(&expr[..]);
-    if enfa.is_err() {
-        return Err(enfa.unwrap_err().to_string());
-    }
-    let enfa = enfa.unwrap();
+pub fn get_debug_graph_json(expr: &str) -> Result<FaRep, JsValue> {
+    let enfa = get_enfa_from_regex(expr)?;
 
     let mut nfa = enfa.to_nfa();
     nfa.remove_unreachable_nodes();
 
-    return Ok(nfa.minimized_dfa().to_fa_rep());
+    Ok(nfa.minimized_dfa().to_fa_rep())
+}
+
+/// Builds the ε-NFA of `expr`, or returns every error found in it.
+pub fn build_enfa(expr: &str, config: &Config) -> Result<ENfa, Vec<RegexError>> {
+    let ast = parse_regex(expr, config)?;
+    gen_epsilon_nfa_from_expr(&ast, config).map_err(|err| vec![err])
 }
 
+/// Builds the ε-NFA of `expr`. On failure, throws a `RegexErrorRep` pointing
+/// at the first offending part of `expr`. Only usable from JavaScript, as the
+/// error is a `JsValue`; use `build_enfa` from Rust.
 #[wasm_bindgen]
-pub fn get_enfa_from_regex(expr: &str) -> Result<ENfa, String> {
-    let expr = tokens_to_postfix(&mut RegexTokenizer::from_string(expr))
-        .context("Failed to convert to postfix");
-    if expr.is_err() {
-        return Err(expr.unwrap_err().to_string());
-    }
-    let expr = expr.unwrap();
+pub fn get_enfa_from_regex(expr: &str) -> Result<ENfa, JsValue> {
+    build_enfa(expr, &Config::default()).map_err(|errs| errs[0].to_error_rep(expr).into())
+}
 
-    let enfa = gen_epsilon_nfa_from_expr(&expr[..]);
-    if enfa.is_err() {
-        return Err(enfa.unwrap_err().to_string());
+/// Returns every error in `expr`, each with a suggested fix where there is
+/// one, or nothing if `expr` is fine.
+#[wasm_bindgen]
+pub fn get_regex_errors(expr: &str) -> Vec<RegexErrorRep> {
+    match build_enfa(expr, &Config::default()) {
+        Ok(_) => vec![],
+        Err(errs) => errs.iter().map(|err| err.to_error_rep(expr)).collect(),
     }
+}
 
-    Ok(enfa.unwrap())
+/// Returns `expr` with every suggested fix made. Fixing some errors may bring
+/// others to light, so the result is not always free of errors.
+#[wasm_bindgen]
+pub fn get_fixed_regex(expr: &str) -> String {
+    match build_enfa(expr, &Config::default()) {
+        Ok(_) => expr.to_string(),
+        Err(errs) => apply_fixes(expr, &errs),
+    }
 }
 
 #[wasm_bindgen]
@@ -63,6 +88,18 @@ impl ENfa {
         nfa.remove_unreachable_nodes();
         nfa
     }
+
+    /// Returns the ε-NFA used to search for the pattern inside a larger text,
+    /// see `ENfa::unanchored`.
+    pub fn get_unanchored(&self) -> ENfa {
+        self.clone().unanchored()
+    }
+
+    /// Returns the ε-NFA reading UTF-8 bytes instead of chars, see
+    /// `ENfa::to_utf8`.
+    pub fn get_utf8(&self) -> ENfa {
+        self.to_utf8()
+    }
 }
 
 #[wasm_bindgen]
@@ -70,4 +107,15 @@ impl Nfa {
     pub fn get_minimized_dfa(&self) -> Nfa {
         self.clone().minimized_dfa()
     }
+
+    /// Returns the DFA from the subset construction, with a transition for
+    /// every char, see `Nfa::determinize`.
+    pub fn get_dfa(&self) -> Dfa {
+        self.clone().determinize()
+    }
+
+    /// Returns the minimal DFA, built with the given algorithm.
+    pub fn get_minimized_dfa_with(&self, minimizer: Minimizer) -> Nfa {
+        self.clone().minimized_dfa_with(minimizer)
+    }
 }
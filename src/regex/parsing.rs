@@ -1,128 +1,1509 @@
-use super::tokens::{RegexTokenizer, Token};
-use anyhow::{Error, Result};
+use std::fmt;
 
-#[derive(Debug, Clone, Copy)]
+use super::{
+    charset::CharSet,
+    config::Config,
+    tokens::{RegexTokenizer, Token},
+};
+use wasm_bindgen::prelude::*;
+
+/// A range of byte offsets into the pattern, from `start` up to but not
+/// including `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// Returns the smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RegexOp {
     Union,
     Star,
+    Plus,
+    Optional,
+    /// Bounded repetition, `{min}`, `{min,}` or `{min,max}`. A missing `max`
+    /// means there is no upper bound.
+    Repeat {
+        min: u32,
+        max: Option<u32>,
+    },
     Concat,
+    /// Compiles the operand with the given flags.
+    SetFlags(Flags),
+    /// Strings matched by both operands, `&`.
+    Intersection,
+    /// Strings matched by the first operand but not the second, `--`.
+    Difference,
+    /// Strings not matched by the operand, `~`.
+    Complement,
+    /// Records where the operand matched as the capture group with the given
+    /// index.
+    Capture(u32),
 }
 
-#[derive(Debug, Clone, Copy)]
-enum OpStkItem {
-    Op(RegexOp),
-    Parens,
+/// A flag that can be turned on or off within a pattern, as in `(?i)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    /// `i`, for matching letters regardless of case.
+    CaseInsensitive,
+    /// `m`, for `^` and `$` to match at the start and end of every line.
+    MultiLine,
+    /// `s`, for `.` to match `\n` as well.
+    DotMatchesNewLine,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum ExprUnit {
-    Char(char),
-    Op(RegexOp),
+impl Flag {
+    pub fn from_char(c: char) -> Option<Flag> {
+        match c {
+            'i' => Some(Flag::CaseInsensitive),
+            'm' => Some(Flag::MultiLine),
+            's' => Some(Flag::DotMatchesNewLine),
+            _ => None,
+        }
+    }
+}
+
+/// The state of every flag at some point of a pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Flags {
+    pub case_insensitive: bool,
+    pub multi_line: bool,
+    pub dot_matches_new_line: bool,
+}
+
+impl Flags {
+    /// Returns the flags a pattern starts out with.
+    pub fn from_config(config: &Config) -> Self {
+        Flags {
+            case_insensitive: config.get_case_insensitive(),
+            multi_line: config.get_multi_line(),
+            dot_matches_new_line: config.get_dot_matches_new_line(),
+        }
+    }
+
+    pub fn set(&mut self, flag: Flag, on: bool) {
+        match flag {
+            Flag::CaseInsensitive => self.case_insensitive = on,
+            Flag::MultiLine => self.multi_line = on,
+            Flag::DotMatchesNewLine => self.dot_matches_new_line = on,
+        }
+    }
+
+    /// Returns the letter of each flag in a flag group, along with whether
+    /// it is on.
+    fn letters(self) -> [(char, bool); 3] {
+        [
+            ('i', self.case_insensitive),
+            ('m', self.multi_line),
+            ('s', self.dot_matches_new_line),
+        ]
+    }
+
+    /// Writes the flags that differ from `outer` the way a flag group turns
+    /// them on or off, as in the `i-s` of `(?i-s:...)`.
+    fn fmt_changes(self, f: &mut fmt::Formatter<'_>, outer: Flags) -> fmt::Result {
+        let changes: Vec<_> = self
+            .letters()
+            .into_iter()
+            .zip(outer.letters())
+            .filter(|(flag, outer)| flag != outer)
+            .map(|(flag, _)| flag)
+            .collect();
+        write_flag_letters(f, &changes)
+    }
 }
 
-fn precedence(op: &RegexOp) -> i32 {
+/// Writes the letters of `flags` with the ones turned off after a `-`.
+fn write_flag_letters(f: &mut fmt::Formatter<'_>, flags: &[(char, bool)]) -> fmt::Result {
+    let on: String = flags.iter().filter(|(_, on)| *on).map(|(c, _)| c).collect();
+    let off: String = flags.iter().filter(|(_, on)| !on).map(|(c, _)| c).collect();
+    if off.is_empty() {
+        write!(f, "{}", on)
+    } else {
+        write!(f, "{}-{}", on, off)
+    }
+}
+
+/// Writes the flags the way a flag group turns each of them on or off, as in
+/// the `i-ms` of `(?i-ms:...)`.
+impl fmt::Display for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_flag_letters(f, &self.letters())
+    }
+}
+
+/// Errors for malformed bounded repetitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepetitionError {
+    /// A `{` was never closed by a `}`.
+    Unclosed,
+    /// The lower bound is missing, as in `a{}` or `a{,3}`.
+    MissingBound,
+    /// Something other than a digit or a comma appeared inside the braces.
+    UnexpectedChar(char),
+    /// The upper bound is below the lower bound, as in `a{5,2}`.
+    InvertedBounds { min: u32, max: u32 },
+    /// A bound, multiplied by the counts of the repetitions around it, is
+    /// above the configured repetition limit.
+    ExceedsLimit { count: u32, limit: u32 },
+}
+
+impl fmt::Display for RepetitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepetitionError::Unclosed => write!(f, "Invalid repetition: unclosed '{{'"),
+            RepetitionError::MissingBound => {
+                write!(f, "Invalid repetition: missing lower bound")
+            }
+            RepetitionError::UnexpectedChar(c) => {
+                write!(f, "Invalid repetition: unexpected '{}' in bounds", c)
+            }
+            RepetitionError::InvertedBounds { min, max } => write!(
+                f,
+                "Invalid repetition: upper bound {} is less than lower bound {}",
+                max, min
+            ),
+            RepetitionError::ExceedsLimit { count, limit } => write!(
+                f,
+                "Invalid repetition: count {} exceeds the limit of {}",
+                count, limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RepetitionError {}
+
+/// The different ways a pattern can be malformed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegexErrorKind {
+    /// A backslash at the very end of the pattern.
+    TrailingBackslash,
+    /// A backslash before a char with no escape meaning, like `\q`.
+    UnknownEscape(char),
+    /// A `\u` not followed by `{`.
+    UnicodeEscapeMissingBrace,
+    /// A `\u{` never closed by a `}`.
+    UnclosedUnicodeEscape,
+    /// Something other than a hex digit inside `\u{...}`.
+    InvalidHexDigit(char),
+    /// A `\u{...}` with no digits, or more than six.
+    HexDigitCount,
+    /// A `\u{...}` naming a surrogate, or a code point past `U+10FFFF`.
+    InvalidCodePoint(u32),
+    /// A `\p` or `\P` at the very end of the pattern.
+    TrailingProperty,
+    /// A `\p{` never closed by a `}`.
+    UnclosedProperty,
+    /// A `\p{...}` naming no known general category or script.
+    UnknownProperty(String),
+    /// A `[` never closed by a `]`.
+    UnclosedClass,
+    /// A class range whose end comes before its start, like `[z-a]`.
+    RangeOutOfOrder { lo: char, hi: char },
+    /// A malformed or too large bounded repetition.
+    Repetition(RepetitionError),
+    /// A `(` never closed by a `)`.
+    UnbalancedOpenParen,
+    /// A `)` with no `(` to close.
+    UnbalancedCloseParen,
+    /// A postfix operator with nothing before it to apply to, as in `*a` or
+    /// `a|+`.
+    MissingOperand(RegexOp),
+    /// A `^` or `$` inside an operand of `&`, `--` or `~`. Each operand is
+    /// turned into a DFA on its own, where the anchor could only be checked
+    /// against the ends of the operand rather than of the text.
+    AnchorInOperand(RegexOp),
+    /// A char other than a known flag or `-` in a flag group, as in `(?x)`.
+    UnknownFlag(char),
+    /// A flag group with no flags, `(?)` or `(?-)`.
+    EmptyFlags,
+    /// A `(?` never closed by a `)`.
+    UnclosedFlags,
+    /// A `(?<` never closed by a `>`.
+    UnclosedGroupName,
+    /// A group name which is empty or has chars other than letters, digits
+    /// and `_` in it, or starts with a digit.
+    InvalidGroupName(String),
+    /// A group name already given to an earlier group.
+    DuplicateGroupName(String),
+}
+
+impl RegexErrorKind {
+    /// Returns a short, stable identifier for the kind, for tooling to match
+    /// on.
+    pub fn name(&self) -> &'static str {
+        match self {
+            RegexErrorKind::TrailingBackslash => "trailing_backslash",
+            RegexErrorKind::UnknownEscape(_) => "unknown_escape",
+            RegexErrorKind::UnicodeEscapeMissingBrace => "unicode_escape_missing_brace",
+            RegexErrorKind::UnclosedUnicodeEscape => "unclosed_unicode_escape",
+            RegexErrorKind::InvalidHexDigit(_) => "invalid_hex_digit",
+            RegexErrorKind::HexDigitCount => "hex_digit_count",
+            RegexErrorKind::InvalidCodePoint(_) => "invalid_code_point",
+            RegexErrorKind::TrailingProperty => "trailing_property",
+            RegexErrorKind::UnclosedProperty => "unclosed_property",
+            RegexErrorKind::UnknownProperty(_) => "unknown_property",
+            RegexErrorKind::UnclosedClass => "unclosed_class",
+            RegexErrorKind::RangeOutOfOrder { .. } => "range_out_of_order",
+            RegexErrorKind::Repetition(RepetitionError::Unclosed) => "unclosed_repetition",
+            RegexErrorKind::Repetition(RepetitionError::MissingBound) => "repetition_missing_bound",
+            RegexErrorKind::Repetition(RepetitionError::UnexpectedChar(_)) => {
+                "repetition_unexpected_char"
+            }
+            RegexErrorKind::Repetition(RepetitionError::InvertedBounds { .. }) => {
+                "repetition_inverted_bounds"
+            }
+            RegexErrorKind::Repetition(RepetitionError::ExceedsLimit { .. }) => {
+                "repetition_exceeds_limit"
+            }
+            RegexErrorKind::UnbalancedOpenParen => "unbalanced_open_paren",
+            RegexErrorKind::UnbalancedCloseParen => "unbalanced_close_paren",
+            RegexErrorKind::MissingOperand(_) => "missing_operand",
+            RegexErrorKind::AnchorInOperand(_) => "anchor_in_operand",
+            RegexErrorKind::UnknownFlag(_) => "unknown_flag",
+            RegexErrorKind::EmptyFlags => "empty_flags",
+            RegexErrorKind::UnclosedFlags => "unclosed_flags",
+            RegexErrorKind::UnclosedGroupName => "unclosed_group_name",
+            RegexErrorKind::InvalidGroupName(_) => "invalid_group_name",
+            RegexErrorKind::DuplicateGroupName(_) => "duplicate_group_name",
+        }
+    }
+
+    /// Returns a hint on how the pattern may be fixed, if there is an obvious
+    /// one.
+    pub fn help(&self) -> Option<String> {
+        let help = match self {
+            RegexErrorKind::TrailingBackslash => "use '\\\\' to match a backslash".to_string(),
+            RegexErrorKind::UnknownEscape(c) if c.is_alphanumeric() => {
+                format!("use '{}' without the backslash to match it literally", c)
+            }
+            RegexErrorKind::UnicodeEscapeMissingBrace
+            | RegexErrorKind::UnclosedUnicodeEscape
+            | RegexErrorKind::InvalidHexDigit(_)
+            | RegexErrorKind::HexDigitCount => {
+                "write code points as '\\u{...}' with 1 to 6 hex digits".to_string()
+            }
+            RegexErrorKind::UnclosedProperty | RegexErrorKind::TrailingProperty => {
+                "write properties as '\\pL' or '\\p{Greek}'".to_string()
+            }
+            RegexErrorKind::UnknownProperty(_) => {
+                "properties are general categories like 'Lu' or scripts like 'Greek'".to_string()
+            }
+            RegexErrorKind::UnclosedClass => {
+                "add a ']' to close the class, or use '\\[' to match a bracket".to_string()
+            }
+            RegexErrorKind::RangeOutOfOrder { lo, hi } => {
+                format!("write the range as '{}-{}'", hi, lo)
+            }
+            RegexErrorKind::Repetition(RepetitionError::Unclosed)
+            | RegexErrorKind::Repetition(RepetitionError::UnexpectedChar(_)) => {
+                "use '\\{' to match a brace".to_string()
+            }
+            RegexErrorKind::Repetition(RepetitionError::MissingBound) => {
+                "use '{0,n}' to allow zero repetitions".to_string()
+            }
+            RegexErrorKind::Repetition(RepetitionError::InvertedBounds { min, max }) => {
+                format!("write the bounds as '{{{},{}}}'", max, min)
+            }
+            RegexErrorKind::Repetition(RepetitionError::ExceedsLimit { .. }) => {
+                "raise the repetition limit in the config".to_string()
+            }
+            RegexErrorKind::UnbalancedOpenParen => "add a ')' to close the group".to_string(),
+            RegexErrorKind::UnbalancedCloseParen => {
+                "remove the ')', or use '\\)' to match a parenthesis".to_string()
+            }
+            RegexErrorKind::MissingOperand(_) => {
+                "put something to repeat before the operator, or escape it".to_string()
+            }
+            RegexErrorKind::AnchorInOperand(_) => {
+                "move the anchor out of the operator, as in '^(?:a&b)'".to_string()
+            }
+            RegexErrorKind::UnknownFlag(_) | RegexErrorKind::EmptyFlags => {
+                "the flags are 'i', 'm' and 's', as in '(?i)', '(?-m)' or '(?is:...)'".to_string()
+            }
+            RegexErrorKind::UnclosedFlags => "add a ')' to close the flags".to_string(),
+            RegexErrorKind::UnclosedGroupName => "add a '>' to close the name".to_string(),
+            RegexErrorKind::InvalidGroupName(_) => {
+                "group names start with a letter or '_', followed by letters, digits or '_'"
+                    .to_string()
+            }
+            RegexErrorKind::DuplicateGroupName(_) => {
+                "give each group a name of its own".to_string()
+            }
+            RegexErrorKind::UnknownEscape(_) | RegexErrorKind::InvalidCodePoint(_) => return None,
+        };
+        Some(help)
+    }
+}
+
+/// Returns how an operator is called in error messages.
+fn op_name(op: RegexOp) -> &'static str {
     match op {
-        RegexOp::Union => 1,
-        RegexOp::Concat => 2,
-        RegexOp::Star => 3,
+        RegexOp::Star => "star",
+        RegexOp::Plus => "plus",
+        RegexOp::Optional => "question mark",
+        RegexOp::Repeat { .. } => "repetition",
+        RegexOp::Union => "pipe",
+        RegexOp::Concat => "concatenation",
+        RegexOp::SetFlags(_) => "flags",
+        RegexOp::Intersection => "ampersand",
+        RegexOp::Difference => "double dash",
+        RegexOp::Complement => "tilde",
+        RegexOp::Capture(_) => "group",
     }
 }
 
-fn add_operator(post_expr: &mut Vec<ExprUnit>, op_stk: &mut Vec<OpStkItem>, op: RegexOp) {
-    while let Some(OpStkItem::Op(stk_op)) = op_stk.last() {
-        if precedence(stk_op) < precedence(&op) {
-            break;
+impl fmt::Display for RegexErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegexErrorKind::TrailingBackslash => write!(f, "Invalid escape: trailing backslash"),
+            RegexErrorKind::UnknownEscape(c) => {
+                write!(f, "Invalid escape: unknown escape '\\{}'", c)
+            }
+            RegexErrorKind::UnicodeEscapeMissingBrace => {
+                write!(f, "Invalid escape: expected '{{' after '\\u'")
+            }
+            RegexErrorKind::UnclosedUnicodeEscape => {
+                write!(f, "Invalid escape: unclosed '\\u{{'")
+            }
+            RegexErrorKind::InvalidHexDigit(c) => {
+                write!(f, "Invalid escape: '{}' is not a hex digit", c)
+            }
+            RegexErrorKind::HexDigitCount => write!(
+                f,
+                "Invalid escape: '\\u{{...}}' takes between 1 and 6 hex digits"
+            ),
+            RegexErrorKind::InvalidCodePoint(code) => write!(
+                f,
+                "Invalid escape: U+{:X} is not a Unicode scalar value",
+                code
+            ),
+            RegexErrorKind::TrailingProperty => write!(f, "Invalid escape: trailing '\\p'"),
+            RegexErrorKind::UnclosedProperty => write!(f, "Invalid escape: unclosed '\\p{{'"),
+            RegexErrorKind::UnknownProperty(name) => {
+                write!(f, "Invalid escape: unknown Unicode property '{}'", name)
+            }
+            RegexErrorKind::UnclosedClass => write!(f, "Invalid class: unclosed '['"),
+            RegexErrorKind::RangeOutOfOrder { lo, hi } => {
+                write!(f, "Invalid class: range '{}-{}' is out of order", lo, hi)
+            }
+            RegexErrorKind::Repetition(err) => write!(f, "{}", err),
+            RegexErrorKind::UnbalancedOpenParen => {
+                write!(f, "Invalid expression: unbalanced open parenthesis")
+            }
+            RegexErrorKind::UnbalancedCloseParen => {
+                write!(f, "Invalid expression: unbalanced closed parenthesis")
+            }
+            RegexErrorKind::MissingOperand(op) => {
+                write!(f, "Invalid expression: {} after operator", op_name(*op))
+            }
+            RegexErrorKind::AnchorInOperand(op) => write!(
+                f,
+                "Invalid expression: anchor inside an operand of the {}",
+                op_name(*op)
+            ),
+            RegexErrorKind::UnknownFlag(c) => write!(f, "Invalid flags: unknown flag '{}'", c),
+            RegexErrorKind::EmptyFlags => write!(f, "Invalid flags: no flags given"),
+            RegexErrorKind::UnclosedFlags => write!(f, "Invalid flags: unclosed '(?'"),
+            RegexErrorKind::UnclosedGroupName => write!(f, "Invalid group: unclosed '(?<'"),
+            RegexErrorKind::InvalidGroupName(name) => {
+                write!(f, "Invalid group: '{}' is not a valid name", name)
+            }
+            RegexErrorKind::DuplicateGroupName(name) => {
+                write!(f, "Invalid group: duplicate name '{}'", name)
+            }
+        }
+    }
+}
+
+/// An edit to the pattern which gets rid of an error, replacing the text at
+/// `span` with `replacement`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    pub span: Span,
+    /// The text at `span` before the edit.
+    pub original: String,
+    pub replacement: String,
+}
+
+impl Fix {
+    pub fn new(span: Span, original: &str, replacement: &str) -> Self {
+        Fix {
+            span,
+            original: original.to_string(),
+            replacement: replacement.to_string(),
+        }
+    }
+
+    pub fn insert(at: usize, text: &str) -> Self {
+        Fix::new(Span::new(at, at), "", text)
+    }
+
+    pub fn remove(span: Span, original: &str) -> Self {
+        Fix::new(span, original, "")
+    }
+
+    /// Returns `pattern` with the edit made.
+    pub fn apply(&self, pattern: &str) -> String {
+        let mut fixed = pattern.to_string();
+        fixed.replace_range(self.span.start..self.span.end, &self.replacement);
+        fixed
+    }
+}
+
+/// Returns `pattern` with the fixes of all the `errors` made. Fixes are made
+/// from the back of the pattern to the front, so that their spans stay valid,
+/// and any fix overlapping one already made is left out.
+pub fn apply_fixes(pattern: &str, errors: &[RegexError]) -> String {
+    let mut fixes: Vec<&Fix> = errors.iter().filter_map(|err| err.fix.as_ref()).collect();
+    fixes.sort_by_key(|fix| (fix.span.start, fix.span.end));
+
+    let mut fixed = pattern.to_string();
+    let mut limit = usize::MAX;
+    for fix in fixes.into_iter().rev() {
+        if fix.span.end <= limit {
+            fixed = fix.apply(&fixed);
+            limit = fix.span.start;
+        }
+    }
+    fixed
+}
+
+/// Describes the edit, as in "insert `)` at 14" or "remove `*` at 3".
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.original.is_empty() {
+            write!(f, "insert `{}` at {}", self.replacement, self.span.start)
+        } else if self.replacement.is_empty() {
+            write!(f, "remove `{}` at {}", self.original, self.span.start)
+        } else {
+            write!(
+                f,
+                "replace `{}` at {} with `{}`",
+                self.original, self.span.start, self.replacement
+            )
+        }
+    }
+}
+
+/// An error in a pattern, along with the part of the pattern it is about and
+/// possibly a suggested fix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegexError {
+    pub kind: RegexErrorKind,
+    pub span: Span,
+    pub fix: Option<Fix>,
+}
+
+impl RegexError {
+    pub fn new(kind: RegexErrorKind, span: Span) -> Self {
+        RegexError {
+            kind,
+            span,
+            fix: None,
         }
-        if let Some(OpStkItem::Op(stk_op)) = op_stk.pop() {
-            post_expr.push(ExprUnit::Op(stk_op));
+    }
+
+    pub fn with_fix(mut self, fix: Fix) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+
+    pub fn help(&self) -> Option<String> {
+        self.kind.help()
+    }
+
+    /// Returns the error in a form that can be handed over to JS, with the
+    /// spans converted to UTF-16 offsets into `pattern`, the string the error
+    /// came from.
+    pub fn to_error_rep(&self, pattern: &str) -> RegexErrorRep {
+        let utf16_offset =
+            |offset: usize| pattern[..offset.min(pattern.len())].encode_utf16().count();
+        let utf16_span = |span: Span| Span::new(utf16_offset(span.start), utf16_offset(span.end));
+
+        let span = utf16_span(self.span);
+        RegexErrorRep {
+            start: span.start,
+            end: span.end,
+            kind: self.kind.name().to_string(),
+            message: self.kind.to_string(),
+            help: self.help(),
+            fix: self.fix.as_ref().map(|fix| {
+                let fix = Fix {
+                    span: utf16_span(fix.span),
+                    ..fix.clone()
+                };
+                FixRep {
+                    start: fix.span.start,
+                    end: fix.span.end,
+                    replacement: fix.replacement.clone(),
+                    message: fix.to_string(),
+                }
+            }),
         }
     }
-    op_stk.push(OpStkItem::Op(op));
 }
 
-pub fn tokens_to_postfix(tokens: &mut RegexTokenizer) -> Result<Vec<ExprUnit>> {
-    let mut post_expr = vec![];
-    let mut op_stk = vec![];
+impl fmt::Display for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}..{}", self.kind, self.span.start, self.span.end)
+    }
+}
+
+impl std::error::Error for RegexError {}
+
+/// A `RegexError` as seen from JS. The spans are in UTF-16 code units, so that
+/// they index straight into the JS string of the pattern.
+#[wasm_bindgen(getter_with_clone)]
+pub struct RegexErrorRep {
+    pub start: usize,
+    pub end: usize,
+    pub kind: String,
+    pub message: String,
+    pub help: Option<String>,
+    pub fix: Option<FixRep>,
+}
 
-    let mut last_token = false;
-    for token in tokens {
-        match token {
-            Token::Char(c) => {
-                if last_token {
-                    add_operator(&mut post_expr, &mut op_stk, RegexOp::Concat);
+/// A `Fix` as seen from JS. Applying it replaces the chars from `start` up to
+/// `end` with `replacement`.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone)]
+pub struct FixRep {
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprUnit {
+    Char(char),
+    Class(CharSet),
+    /// Any char, with `\n` included only if the config says so.
+    Dot,
+    /// The empty string.
+    Epsilon,
+    Anchor(Anchor),
+    Op(RegexOp),
+}
+
+/// An assertion matching the empty string at some positions of the text only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    /// `^`, at the start of the text, or of any line in multi-line mode.
+    Start,
+    /// `$`, at the end of the text, or of any line in multi-line mode.
+    End,
+}
+
+/// A node of a parsed regex, along with the part of the pattern it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ast {
+    pub kind: AstKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AstKind {
+    /// The empty string, either written as `\e` or left implicit as in `a|`.
+    Epsilon,
+    Char(char),
+    /// A set of chars. The empty set matches nothing at all.
+    Class(CharSet),
+    /// Any char, with `\n` included only if the config says so.
+    Dot,
+    /// `^` or `$`, depending on the multi-line flag for what counts as a line.
+    Anchor(Anchor),
+    Star(Box<Ast>),
+    Plus(Box<Ast>),
+    Optional(Box<Ast>),
+    Repeat {
+        ast: Box<Ast>,
+        min: u32,
+        max: Option<u32>,
+    },
+    /// Two or more regexes one after the other.
+    Concat(Vec<Ast>),
+    /// Two or more alternatives.
+    Union(Vec<Ast>),
+    /// Two or more regexes which all have to match, `a&b`.
+    Intersection(Vec<Ast>),
+    /// A regex which has to match while the second doesn't, `a--b`.
+    Difference(Box<Ast>, Box<Ast>),
+    /// Matches exactly the strings the regex doesn't, `~a`.
+    Complement(Box<Ast>),
+    /// A regex to be compiled with the given flags, either in a scoped flag
+    /// group like `(?i:...)`, or from a flag group like `(?i)` up to the end
+    /// of the group enclosing it.
+    Flags {
+        flags: Flags,
+        ast: Box<Ast>,
+    },
+    /// A group in parentheses, capturing where the regex inside matched.
+    /// Groups are numbered from 1 in the order they open, and may also be
+    /// named, as in `(?<name>...)`.
+    Group {
+        index: u32,
+        name: Option<String>,
+        ast: Box<Ast>,
+    },
+}
+
+impl Ast {
+    pub fn new(kind: AstKind, span: Span) -> Self {
+        Ast { kind, span }
+    }
+
+    /// Returns the names of the capture groups, indexed by group number. The
+    /// whole match counts as group 0, and neither it nor unnamed groups have
+    /// a name.
+    pub fn capture_names(&self) -> Vec<Option<String>> {
+        let mut names = vec![None];
+        self.push_capture_names(&mut names);
+        names
+    }
+
+    fn push_capture_names(&self, names: &mut Vec<Option<String>>) {
+        match &self.kind {
+            AstKind::Epsilon
+            | AstKind::Char(_)
+            | AstKind::Class(_)
+            | AstKind::Dot
+            | AstKind::Anchor(_) => {}
+            AstKind::Star(ast)
+            | AstKind::Plus(ast)
+            | AstKind::Optional(ast)
+            | AstKind::Repeat { ast, .. }
+            | AstKind::Complement(ast)
+            | AstKind::Flags { ast, .. } => ast.push_capture_names(names),
+            AstKind::Difference(left, right) => {
+                left.push_capture_names(names);
+                right.push_capture_names(names);
+            }
+            AstKind::Concat(asts) | AstKind::Union(asts) | AstKind::Intersection(asts) => {
+                for ast in asts {
+                    ast.push_capture_names(names);
+                }
+            }
+            AstKind::Group { index, name, ast } => {
+                let index = usize::try_from(*index).unwrap();
+                if names.len() <= index {
+                    names.resize(index + 1, None);
                 }
-                post_expr.push(ExprUnit::Char(c));
-                last_token = true;
+                names[index] = name.clone();
+                ast.push_capture_names(names);
             }
+        }
+    }
 
-            Token::Star => {
-                if !last_token {
-                    return Err(Error::msg("Invalid expression: star after operator"));
+    /// Returns, for each capture group by group number, one past the number
+    /// of the last group nested inside of it. Groups are numbered in the
+    /// order they open, so the groups nested inside group `i` are the ones
+    /// from `i + 1` up to that. Every group is nested inside group 0.
+    pub fn nested_captures(&self) -> Vec<usize> {
+        let mut nested = vec![0];
+        self.push_nested_captures(&mut nested);
+        nested[0] = nested.len();
+        nested
+    }
+
+    fn push_nested_captures(&self, nested: &mut Vec<usize>) {
+        match &self.kind {
+            AstKind::Epsilon
+            | AstKind::Char(_)
+            | AstKind::Class(_)
+            | AstKind::Dot
+            | AstKind::Anchor(_) => {}
+            AstKind::Star(ast)
+            | AstKind::Plus(ast)
+            | AstKind::Optional(ast)
+            | AstKind::Repeat { ast, .. }
+            | AstKind::Complement(ast)
+            | AstKind::Flags { ast, .. } => ast.push_nested_captures(nested),
+            AstKind::Difference(left, right) => {
+                left.push_nested_captures(nested);
+                right.push_nested_captures(nested);
+            }
+            AstKind::Concat(asts) | AstKind::Union(asts) | AstKind::Intersection(asts) => {
+                for ast in asts {
+                    ast.push_nested_captures(nested);
                 }
-                add_operator(&mut post_expr, &mut op_stk, RegexOp::Star);
-                if let Some(OpStkItem::Op(stk_op)) = op_stk.pop() {
-                    post_expr.push(ExprUnit::Op(stk_op));
+            }
+            AstKind::Group { index, ast, .. } => {
+                let index = usize::try_from(*index).unwrap();
+                if nested.len() <= index {
+                    nested.resize(index + 1, 0);
                 }
-                last_token = true;
+                ast.push_nested_captures(nested);
+                nested[index] = nested.len();
             }
+        }
+    }
+
+    /// Returns the postfix form of the regex, as printed by
+    /// `get_debug_postexpr_string`.
+    pub fn to_postfix(&self) -> Vec<ExprUnit> {
+        let mut post_expr = vec![];
+        self.push_postfix(&mut post_expr);
+        post_expr
+    }
 
-            Token::Pipe => {
-                if !last_token {
-                    return Err(Error::msg("Invalid expression: pipe after operator"));
+    fn push_postfix(&self, post_expr: &mut Vec<ExprUnit>) {
+        match &self.kind {
+            AstKind::Epsilon => post_expr.push(ExprUnit::Epsilon),
+            AstKind::Char(c) => post_expr.push(ExprUnit::Char(*c)),
+            AstKind::Class(set) => post_expr.push(ExprUnit::Class(set.clone())),
+            AstKind::Dot => post_expr.push(ExprUnit::Dot),
+            AstKind::Anchor(anchor) => post_expr.push(ExprUnit::Anchor(*anchor)),
+            AstKind::Star(ast) => {
+                ast.push_postfix(post_expr);
+                post_expr.push(ExprUnit::Op(RegexOp::Star));
+            }
+            AstKind::Plus(ast) => {
+                ast.push_postfix(post_expr);
+                post_expr.push(ExprUnit::Op(RegexOp::Plus));
+            }
+            AstKind::Optional(ast) => {
+                ast.push_postfix(post_expr);
+                post_expr.push(ExprUnit::Op(RegexOp::Optional));
+            }
+            AstKind::Repeat { ast, min, max } => {
+                ast.push_postfix(post_expr);
+                post_expr.push(ExprUnit::Op(RegexOp::Repeat {
+                    min: *min,
+                    max: *max,
+                }));
+            }
+            AstKind::Flags { flags, ast } => {
+                ast.push_postfix(post_expr);
+                post_expr.push(ExprUnit::Op(RegexOp::SetFlags(*flags)));
+            }
+            AstKind::Group { index, ast, .. } => {
+                ast.push_postfix(post_expr);
+                post_expr.push(ExprUnit::Op(RegexOp::Capture(*index)));
+            }
+            AstKind::Difference(left, right) => {
+                left.push_postfix(post_expr);
+                right.push_postfix(post_expr);
+                post_expr.push(ExprUnit::Op(RegexOp::Difference));
+            }
+            AstKind::Complement(ast) => {
+                ast.push_postfix(post_expr);
+                post_expr.push(ExprUnit::Op(RegexOp::Complement));
+            }
+            AstKind::Concat(asts) | AstKind::Union(asts) | AstKind::Intersection(asts) => {
+                let op = match self.kind {
+                    AstKind::Concat(_) => RegexOp::Concat,
+                    AstKind::Union(_) => RegexOp::Union,
+                    _ => RegexOp::Intersection,
+                };
+                for (i, ast) in asts.iter().enumerate() {
+                    ast.push_postfix(post_expr);
+                    if i > 0 {
+                        post_expr.push(ExprUnit::Op(op));
+                    }
                 }
-                add_operator(&mut post_expr, &mut op_stk, RegexOp::Union);
-                last_token = false;
             }
+        }
+    }
+
+    /// How tightly the node binds when printed, from alternatives binding the
+    /// loosest to single atoms and groups binding the tightest.
+    fn precedence(&self) -> u8 {
+        match self.kind {
+            AstKind::Union(_) => 1,
+            AstKind::Intersection(_) | AstKind::Difference(..) => 2,
+            AstKind::Complement(_) => 3,
+            AstKind::Concat(_) => 4,
+            AstKind::Star(_) | AstKind::Plus(_) | AstKind::Optional(_) | AstKind::Repeat { .. } => {
+                5
+            }
+            AstKind::Epsilon
+            | AstKind::Char(_)
+            | AstKind::Class(_)
+            | AstKind::Dot
+            | AstKind::Anchor(_)
+            | AstKind::Group { .. }
+            | AstKind::Flags { .. } => 6,
+        }
+    }
+
+    /// Prints the node, wrapped in a non-capturing group if it binds looser
+    /// than `precedence`.
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, flags: Flags, precedence: u8) -> fmt::Result {
+        if let AstKind::Flags { flags: inner, ast } = &self.kind {
+            // Flags that change nothing print no group of their own.
+            if *inner == flags {
+                return ast.fmt_operand(f, flags, precedence);
+            }
+        }
+        if self.precedence() < precedence {
+            write!(f, "(?:")?;
+            self.fmt_in(f, flags)?;
+            write!(f, ")")
+        } else if let AstKind::Epsilon = self.kind {
+            write!(f, "\\e")
+        } else {
+            self.fmt_in(f, flags)
+        }
+    }
+
+    /// Returns the regex in the pattern syntax, like its `Display` does, but
+    /// for reading back with `config` rather than the default config.
+    pub fn to_pattern(&self, config: &Config) -> String {
+        struct Pattern<'a>(&'a Ast, Flags);
+
+        impl fmt::Display for Pattern<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt_in(f, self.1)
+            }
+        }
 
-            Token::OpenParens => {
-                if last_token {
-                    add_operator(&mut post_expr, &mut op_stk, RegexOp::Concat);
+        Pattern(self, Flags::from_config(config)).to_string()
+    }
+}
+
+/// Chars with a special meaning outside of brackets, which have to be escaped
+/// to be matched literally.
+///
+/// `-` is only special when doubled, as in `a--b`, but is always escaped so
+/// that two literal dashes in a row stay literal.
+const METACHARS: &str = "\\*+?{}[]().|&~-^$";
+
+/// Writes `c` so that it reads back as that same literal char.
+pub fn fmt_literal(f: &mut fmt::Formatter<'_>, c: char) -> fmt::Result {
+    match c {
+        '\n' => write!(f, "\\n"),
+        '\t' => write!(f, "\\t"),
+        '\r' => write!(f, "\\r"),
+        c if METACHARS.contains(c) => write!(f, "\\{}", c),
+        c if c.is_control() => write!(f, "\\u{{{:x}}}", u32::from(c)),
+        c => write!(f, "{}", c),
+    }
+}
+
+/// Prints the regex back in the pattern syntax, using as few parentheses as
+/// possible. Flag groups only mention the flags they change, starting from
+/// the default flags, so a regex parsed with other flags is printed with
+/// `Ast::to_pattern` instead.
+impl fmt::Display for Ast {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_in(f, Flags::default())
+    }
+}
+
+impl Ast {
+    /// Prints the regex where `flags` are in effect.
+    fn fmt_in(&self, f: &mut fmt::Formatter<'_>, flags: Flags) -> fmt::Result {
+        match &self.kind {
+            // Left implicit where it can be, as in `a|` or the empty regex.
+            AstKind::Epsilon => Ok(()),
+            AstKind::Char(c) => fmt_literal(f, *c),
+            AstKind::Class(set) => write!(f, "{}", set),
+            AstKind::Dot => write!(f, "."),
+            AstKind::Anchor(Anchor::Start) => write!(f, "^"),
+            AstKind::Anchor(Anchor::End) => write!(f, "$"),
+            AstKind::Star(ast) => {
+                ast.fmt_operand(f, flags, 5)?;
+                write!(f, "*")
+            }
+            AstKind::Plus(ast) => {
+                ast.fmt_operand(f, flags, 5)?;
+                write!(f, "+")
+            }
+            AstKind::Optional(ast) => {
+                ast.fmt_operand(f, flags, 5)?;
+                write!(f, "?")
+            }
+            AstKind::Repeat { ast, min, max } => {
+                ast.fmt_operand(f, flags, 5)?;
+                match max {
+                    Some(max) if max == min => write!(f, "{{{}}}", min),
+                    Some(max) => write!(f, "{{{},{}}}", min, max),
+                    None => write!(f, "{{{},}}", min),
+                }
+            }
+            AstKind::Concat(asts) => {
+                for ast in asts {
+                    ast.fmt_operand(f, flags, 4)?;
+                }
+                Ok(())
+            }
+            AstKind::Intersection(asts) => {
+                for (i, ast) in asts.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "&")?;
+                    }
+                    ast.fmt_operand(f, flags, 3)?;
                 }
-                op_stk.push(OpStkItem::Parens);
-                last_token = false;
+                Ok(())
             }
+            // Both operators associate to the left, so only the right operand
+            // needs parentheses around another intersection or difference.
+            AstKind::Difference(left, right) => {
+                left.fmt_operand(f, flags, 2)?;
+                write!(f, "--")?;
+                right.fmt_operand(f, flags, 3)
+            }
+            AstKind::Complement(ast) => {
+                write!(f, "~")?;
+                ast.fmt_operand(f, flags, 3)
+            }
+            AstKind::Union(asts) => {
+                for (i, ast) in asts.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "|")?;
+                    }
+                    // Nothing binds looser than alternatives, and empty
+                    // alternatives stay implicit.
+                    ast.fmt_in(f, flags)?;
+                }
+                Ok(())
+            }
+            // Printed as a scoped flag group, whether it was written as one
+            // or not.
+            AstKind::Flags { flags: inner, ast } => {
+                if *inner == flags {
+                    return ast.fmt_in(f, flags);
+                }
+                write!(f, "(?")?;
+                inner.fmt_changes(f, flags)?;
+                write!(f, ":")?;
+                ast.fmt_in(f, *inner)?;
+                write!(f, ")")
+            }
+            AstKind::Group { name, ast, .. } => {
+                match name {
+                    Some(name) => write!(f, "(?<{}>", name)?,
+                    None => write!(f, "(")?,
+                }
+                ast.fmt_in(f, flags)?;
+                write!(f, ")")
+            }
+        }
+    }
+}
 
-            Token::CloseParens => {
-                if !last_token {
-                    return Err(Error::msg(
-                        "Invalid expression: Closed parentheses on incomplete expression",
-                    ));
+/// A recursive-descent parser turning the tokens of a pattern into an `Ast`.
+///
+/// The grammar, from the loosest binding to the tightest, is:
+///
+/// ```text
+/// union        := intersection ('|' intersection)*
+/// intersection := concat (('&' | '--') concat)*
+/// concat       := repeat* ('~' concat)?
+/// repeat       := atom ('*' | '+' | '?' | '{m,n}')*
+/// atom         := char | class | '.' | '\e' | '^' | '$'
+///               | '(' union ')' | '(?<' name '>' union ')'
+///               | '(?' flags ':' union ')'
+/// ```
+///
+/// A `~` thus complements everything after it up to the next `&`, `--`, `|`
+/// or `)`.
+///
+/// A flag group like `(?i)` may appear anywhere in a concat, and applies from
+/// there up to the end of the enclosing group, alternatives included. A
+/// scoped flag group like `(?i:...)` applies only inside of it, and with no
+/// flags, `(?:...)`, is a group that doesn't capture.
+///
+/// The parser doesn't stop at the first error. Stray operators are recorded
+/// and skipped, malformed classes and escapes are read as the empty string,
+/// and unclosed groups are closed at the end of the pattern, so that every
+/// error can be reported in one go without one error causing others.
+struct Parser<'a, 'b> {
+    tokens: &'b mut RegexTokenizer<'a>,
+    lookahead: Option<(Token, Span)>,
+    errors: Vec<RegexError>,
+    flags: Flags,
+    /// The name of each group opened so far, in order.
+    group_names: Vec<Option<String>>,
+    /// The names given to groups by the fixes so far.
+    fixed_group_names: Vec<String>,
+}
+
+impl<'a, 'b> Parser<'a, 'b> {
+    fn new(tokens: &'b mut RegexTokenizer<'a>) -> Self {
+        Parser {
+            lookahead: None,
+            errors: vec![],
+            flags: Flags::from_config(tokens.config()),
+            group_names: vec![],
+            fixed_group_names: vec![],
+            tokens,
+        }
+    }
+
+    fn peek(&mut self) -> Option<&(Token, Span)> {
+        while self.lookahead.is_none() {
+            // Negated classes depend on the flags in effect where they are.
+            self.tokens
+                .set_case_insensitive(self.flags.case_insensitive);
+            match self.tokens.next() {
+                Some(Ok(token)) => self.lookahead = Some(token),
+                Some(Err(err)) => self.errors.push(err),
+                None => break,
+            }
+        }
+        self.lookahead.as_ref()
+    }
+
+    fn bump(&mut self) -> Option<(Token, Span)> {
+        self.peek();
+        self.lookahead.take()
+    }
+
+    /// Returns an empty span right at the next token, or at the end of the
+    /// pattern.
+    fn here(&mut self) -> Span {
+        let offset = match self.peek() {
+            Some((_, span)) => span.start,
+            None => self.tokens.offset(),
+        };
+        Span::new(offset, offset)
+    }
+
+    /// Records an error about the token at `span`, suggesting to remove it.
+    fn stray_token(&mut self, kind: RegexErrorKind, span: Span) {
+        let fix = Fix::remove(span, &self.tokens.pattern()[span.start..span.end]);
+        self.errors.push(RegexError::new(kind, span).with_fix(fix));
+    }
+
+    fn parse(mut self) -> Result<Ast, Vec<RegexError>> {
+        let mut ast = self.union();
+        while let Some(&(Token::CloseParens, span)) = self.peek() {
+            // Carry on as if the `)` weren't there.
+            self.bump();
+            self.stray_token(RegexErrorKind::UnbalancedCloseParen, span);
+            let rest = self.union();
+            let span = ast.span.to(rest.span);
+            ast = Ast::new(AstKind::Concat(vec![ast, rest]), span);
+        }
+        self.check_repetitions(&ast, 1);
+        self.check_anchors(&ast, None);
+
+        if self.errors.is_empty() {
+            Ok(ast)
+        } else {
+            self.errors.sort_by_key(|err| err.span.start);
+            Err(self.errors)
+        }
+    }
+
+    /// Wraps `ast` in a flags node if `flags`, the flags in effect where it
+    /// starts, differ from the `outer` flags in effect around it. This is how
+    /// flags set in one operand carry over to the operands after it.
+    fn with_flags(ast: Ast, flags: Flags, outer: Flags) -> Ast {
+        if flags == outer {
+            return ast;
+        }
+        let span = ast.span;
+        Ast::new(
+            AstKind::Flags {
+                flags,
+                ast: Box::new(ast),
+            },
+            span,
+        )
+    }
+
+    fn union(&mut self) -> Ast {
+        let group_flags = self.flags;
+        let mut alternatives = vec![self.intersection()];
+        while let Some((Token::Pipe, _)) = self.peek() {
+            self.bump();
+            let flags = self.flags;
+            let ast = self.intersection();
+            alternatives.push(Self::with_flags(ast, flags, group_flags));
+        }
+
+        if alternatives.len() == 1 {
+            return alternatives.pop().unwrap();
+        }
+        let span = alternatives[0]
+            .span
+            .to(alternatives[alternatives.len() - 1].span);
+        Ast::new(AstKind::Union(alternatives), span)
+    }
+
+    fn intersection(&mut self) -> Ast {
+        let outer_flags = self.flags;
+        let mut ast = self.concat();
+        loop {
+            let op = match self.peek() {
+                Some((Token::Ampersand, _)) => RegexOp::Intersection,
+                Some((Token::DoubleDash, _)) => RegexOp::Difference,
+                _ => return ast,
+            };
+            self.bump();
+            let flags = self.flags;
+            let right = Self::with_flags(self.concat(), flags, outer_flags);
+            let span = ast.span.to(right.span);
+            ast = match (op, ast.kind) {
+                (RegexOp::Intersection, AstKind::Intersection(mut asts)) => {
+                    asts.push(right);
+                    Ast::new(AstKind::Intersection(asts), span)
                 }
-                while let Some(OpStkItem::Op(_)) = op_stk.last() {
-                    if let Some(OpStkItem::Op(op)) = op_stk.pop() {
-                        post_expr.push(ExprUnit::Op(op));
+                (RegexOp::Intersection, kind) => Ast::new(
+                    AstKind::Intersection(vec![Ast::new(kind, ast.span), right]),
+                    span,
+                ),
+                (_, kind) => Ast::new(
+                    AstKind::Difference(Box::new(Ast::new(kind, ast.span)), Box::new(right)),
+                    span,
+                ),
+            };
+        }
+    }
+
+    fn concat(&mut self) -> Ast {
+        let mut asts = vec![];
+        loop {
+            let op = match self.peek() {
+                None
+                | Some((Token::Pipe, _))
+                | Some((Token::CloseParens, _))
+                | Some((Token::Ampersand, _))
+                | Some((Token::DoubleDash, _)) => break,
+                Some(&(Token::Tilde, span)) => {
+                    self.bump();
+                    // Like flags, a complement reaches up to the end of the
+                    // concat.
+                    let operand = self.concat();
+                    let span = span.to(operand.span);
+                    asts.push(Ast::new(AstKind::Complement(Box::new(operand)), span));
+                    break;
+                }
+                Some(&(Token::Star, span)) => (RegexOp::Star, span),
+                Some(&(Token::Plus, span)) => (RegexOp::Plus, span),
+                Some(&(Token::Question, span)) => (RegexOp::Optional, span),
+                Some(&(Token::Repeat { min, max }, span)) => (RegexOp::Repeat { min, max }, span),
+                Some((Token::SetFlags(_), _)) => {
+                    let (token, span) = self.bump().unwrap();
+                    if let Token::SetFlags(changes) = token {
+                        for (flag, on) in changes {
+                            self.flags.set(flag, on);
+                        }
                     }
+                    // The flags apply to the rest of the concat, which is
+                    // read as a whole.
+                    let flags = self.flags;
+                    let rest = self.concat();
+                    let span = span.to(rest.span);
+                    asts.push(Ast::new(
+                        AstKind::Flags {
+                            flags,
+                            ast: Box::new(rest),
+                        },
+                        span,
+                    ));
+                    break;
+                }
+                _ => {
+                    asts.push(self.repeat());
+                    continue;
                 }
-                if let Some(OpStkItem::Parens) = op_stk.last() {
-                    op_stk.pop();
-                } else {
-                    return Err(Error::msg(
-                        "Invalid expression: Inbalanced closed parentheses",
+            };
+            // A postfix operator with nothing before it to apply to.
+            self.bump();
+            self.stray_token(RegexErrorKind::MissingOperand(op.0), op.1);
+        }
+
+        match asts.len() {
+            0 => Ast::new(AstKind::Epsilon, self.here()),
+            1 => asts.pop().unwrap(),
+            _ => {
+                let span = asts[0].span.to(asts[asts.len() - 1].span);
+                Ast::new(AstKind::Concat(asts), span)
+            }
+        }
+    }
+
+    fn repeat(&mut self) -> Ast {
+        let mut ast = self.atom();
+        loop {
+            let kind = match self.peek() {
+                Some((Token::Star, _)) => AstKind::Star,
+                Some((Token::Plus, _)) => AstKind::Plus,
+                Some((Token::Question, _)) => AstKind::Optional,
+                Some((Token::Repeat { .. }, _)) => |ast| AstKind::Repeat {
+                    ast,
+                    min: 0,
+                    max: None,
+                },
+                _ => return ast,
+            };
+            let (token, span) = self.bump().unwrap();
+            let span = ast.span.to(span);
+            let mut kind = kind(Box::new(ast));
+            if let (AstKind::Repeat { min, max, .. }, Token::Repeat { min: m, max: n }) =
+                (&mut kind, token)
+            {
+                (*min, *max) = (m, n);
+            }
+            ast = Ast::new(kind, span);
+        }
+    }
+
+    /// Returns `name` if neither an earlier group nor a fix has it, or else
+    /// `name` followed by the first number making it a name neither has.
+    fn unused_group_name(&mut self, name: &str) -> String {
+        let taken = |name: &str| {
+            self.group_names.iter().flatten().any(|other| other == name)
+                || self.fixed_group_names.iter().any(|other| other == name)
+        };
+        let name = if taken(name) {
+            (2..)
+                .map(|n| format!("{}{}", name, n))
+                .find(|name| !taken(name))
+                .unwrap()
+        } else {
+            name.to_string()
+        };
+        self.fixed_group_names.push(name.clone());
+        name
+    }
+
+    /// Records an error if `name`, the name of the group opened at `span`, is
+    /// malformed or taken by an earlier group.
+    ///
+    /// The fix for a malformed name has the chars which can't be in a name
+    /// replaced with `_`, and that for a taken one has a number added to the
+    /// name. A group with an empty name is made one with no name.
+    fn check_group_name(&mut self, name: &str, span: Span) {
+        // The name comes right after the `(?<`.
+        let name_span = Span::new(span.start + 3, span.start + 3 + name.len());
+        let mut chars = name.chars();
+        let valid = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+            && chars.all(|c| c.is_alphanumeric() || c == '_');
+        let err = if name.is_empty() {
+            let head = Span::new(span.start, name_span.end + 1);
+            let err = RegexError::new(RegexErrorKind::InvalidGroupName(String::new()), name_span);
+            match self.tokens.pattern().get(head.start..head.end) {
+                Some("(?<>") => err.with_fix(Fix::new(head, "(?<>", "(")),
+                _ => err,
+            }
+        } else if !valid {
+            let mut fixed: String = name
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect();
+            if fixed.starts_with(|c: char| c.is_numeric()) {
+                fixed.insert(0, '_');
+            }
+            let fixed = self.unused_group_name(&fixed);
+            RegexError::new(
+                RegexErrorKind::InvalidGroupName(name.to_string()),
+                name_span,
+            )
+            .with_fix(Fix::new(name_span, name, &fixed))
+        } else if self.group_names.iter().flatten().any(|other| other == name) {
+            let fixed = self.unused_group_name(name);
+            RegexError::new(
+                RegexErrorKind::DuplicateGroupName(name.to_string()),
+                name_span,
+            )
+            .with_fix(Fix::new(name_span, name, &fixed))
+        } else {
+            return;
+        };
+        self.errors.push(err);
+    }
+
+    /// Records an error for every anchor inside an operand of `&`, `--` or
+    /// `~`, `op` being the innermost of those around `ast`, if any.
+    fn check_anchors(&mut self, ast: &Ast, op: Option<RegexOp>) {
+        match &ast.kind {
+            AstKind::Anchor(_) => {
+                if let Some(op) = op {
+                    self.errors.push(RegexError::new(
+                        RegexErrorKind::AnchorInOperand(op),
+                        ast.span,
                     ));
                 }
-                last_token = true;
             }
+            AstKind::Epsilon | AstKind::Char(_) | AstKind::Class(_) | AstKind::Dot => {}
+            AstKind::Star(ast)
+            | AstKind::Plus(ast)
+            | AstKind::Optional(ast)
+            | AstKind::Repeat { ast, .. }
+            | AstKind::Flags { ast, .. }
+            | AstKind::Group { ast, .. } => self.check_anchors(ast, op),
+            AstKind::Complement(ast) => self.check_anchors(ast, Some(RegexOp::Complement)),
+            AstKind::Difference(left, right) => {
+                self.check_anchors(left, Some(RegexOp::Difference));
+                self.check_anchors(right, Some(RegexOp::Difference));
+            }
+            AstKind::Intersection(asts) => {
+                for ast in asts {
+                    self.check_anchors(ast, Some(RegexOp::Intersection));
+                }
+            }
+            AstKind::Concat(asts) | AstKind::Union(asts) => {
+                for ast in asts {
+                    self.check_anchors(ast, op);
+                }
+            }
+        }
+    }
 
-            Token::Backslash => {
-                if last_token {
-                    add_operator(&mut post_expr, &mut op_stk, RegexOp::Concat);
+    /// Records an error for every bounded repetition whose count, multiplied
+    /// by the `copies` made of it by the repetitions around it, is above the
+    /// repetition limit. The fix brings the bounds down to the largest count
+    /// allowed. Repetitions inside one already too large aren't checked, as
+    /// fixing it changes their copies.
+    fn check_repetitions(&mut self, ast: &Ast, copies: u32) {
+        match &ast.kind {
+            AstKind::Epsilon
+            | AstKind::Char(_)
+            | AstKind::Class(_)
+            | AstKind::Dot
+            | AstKind::Anchor(_) => {}
+            AstKind::Repeat {
+                ast: operand,
+                min,
+                max,
+            } => {
+                let limit = self.tokens.config().get_repetition_limit();
+                // Counted the same as by `gen_epsilon_nfa_from_expr`.
+                let count = copies.saturating_mul(max.unwrap_or(*min).max(1));
+                if count <= limit {
+                    return self.check_repetitions(operand, count);
+                }
+                let span = Span::new(operand.span.end, ast.span.end);
+                let err = RegexError::new(
+                    RegexErrorKind::Repetition(RepetitionError::ExceedsLimit { count, limit }),
+                    span,
+                );
+                // Even `{0}` makes a copy of the operand, so there is no fix
+                // if not even one copy is allowed.
+                let bound = limit / copies;
+                if bound == 0 {
+                    return self.errors.push(err);
+                }
+                let (min, max) = ((*min).min(bound), max.map(|max| max.min(bound)));
+                let bounds = match max {
+                    Some(max) if max == min => format!("{{{}}}", min),
+                    Some(max) => format!("{{{},{}}}", min, max),
+                    None => format!("{{{},}}", min),
+                };
+                let original = &self.tokens.pattern()[span.start..span.end];
+                let fix = Fix::new(span, original, &bounds);
+                self.errors.push(err.with_fix(fix));
+            }
+            AstKind::Star(ast)
+            | AstKind::Plus(ast)
+            | AstKind::Optional(ast)
+            | AstKind::Complement(ast)
+            | AstKind::Flags { ast, .. }
+            | AstKind::Group { ast, .. } => self.check_repetitions(ast, copies),
+            AstKind::Difference(left, right) => {
+                self.check_repetitions(left, copies);
+                self.check_repetitions(right, copies);
+            }
+            AstKind::Concat(asts) | AstKind::Union(asts) | AstKind::Intersection(asts) => {
+                for ast in asts {
+                    self.check_repetitions(ast, copies);
                 }
-                post_expr.push(ExprUnit::Char('\\'));
-                last_token = true;
             }
         }
     }
 
-    while let Some(OpStkItem::Op(_)) = op_stk.last() {
-        if let Some(OpStkItem::Op(op)) = op_stk.pop() {
-            post_expr.push(ExprUnit::Op(op));
+    /// Reads the inside of a group opened at `open`, up to and including the
+    /// closing `)`, and returns it along with the span of the whole group.
+    /// Flags set inside the group don't outlast it.
+    fn group(&mut self, open: Span) -> (Ast, Span) {
+        let outer_flags = self.flags;
+        let ast = self.union();
+        self.flags = outer_flags;
+        if let Some(&(Token::CloseParens, close)) = self.peek() {
+            self.bump();
+            return (ast, open.to(close));
         }
+        // Only the end of the pattern stops a union other than `)`, so that
+        // is where the group gets closed.
+        let end = self.tokens.offset();
+        self.errors.push(
+            RegexError::new(RegexErrorKind::UnbalancedOpenParen, open)
+                .with_fix(Fix::insert(end, ")")),
+        );
+        (ast, open.to(Span::new(end, end)))
     }
-    if !op_stk.is_empty() {
-        return Err(Error::msg(
-            "Invalid expression: Inbalanced open parantheses",
-        ));
+
+    fn atom(&mut self) -> Ast {
+        let (token, span) = self.bump().expect("repeat is only called before a token");
+        let kind = match token {
+            Token::Char(c) => AstKind::Char(c),
+            Token::Class(set) => AstKind::Class(set),
+            Token::Dot => AstKind::Dot,
+            Token::Epsilon => AstKind::Epsilon,
+            Token::Caret => AstKind::Anchor(Anchor::Start),
+            Token::Dollar => AstKind::Anchor(Anchor::End),
+            Token::OpenParens | Token::OpenNamedGroup(_) => {
+                let name = match token {
+                    Token::OpenNamedGroup(name) => {
+                        self.check_group_name(&name, span);
+                        Some(name)
+                    }
+                    _ => None,
+                };
+                self.group_names.push(name.clone());
+                let index = u32::try_from(self.group_names.len()).unwrap();
+
+                let (ast, span) = self.group(span);
+                let ast = Box::new(ast);
+                return Ast::new(AstKind::Group { index, name, ast }, span);
+            }
+            Token::OpenFlagGroup(changes) => {
+                let outer_flags = self.flags;
+                for &(flag, on) in &changes {
+                    self.flags.set(flag, on);
+                }
+                let flags = self.flags;
+                let (ast, span) = self.group(span);
+                self.flags = outer_flags;
+                if changes.is_empty() {
+                    return Ast::new(ast.kind, span);
+                }
+                let ast = Box::new(ast);
+                return Ast::new(AstKind::Flags { flags, ast }, span);
+            }
+            Token::Star
+            | Token::Plus
+            | Token::Question
+            | Token::Repeat { .. }
+            | Token::SetFlags(_)
+            | Token::Ampersand
+            | Token::DoubleDash
+            | Token::Tilde
+            | Token::Pipe
+            | Token::CloseParens => unreachable!("concat stops before these"),
+        };
+        Ast::new(kind, span)
     }
+}
+
+/// Parses the tokens into an `Ast`, or returns every error found in them,
+/// ordered by where they are in the pattern.
+pub fn tokens_to_ast(tokens: &mut RegexTokenizer) -> Result<Ast, Vec<RegexError>> {
+    Parser::new(tokens).parse()
+}
+
+pub fn parse_regex(expr: &str, config: &Config) -> Result<Ast, Vec<RegexError>> {
+    tokens_to_ast(&mut RegexTokenizer::with_config(expr, *config))
+}
 
-    Ok(post_expr)
+pub fn tokens_to_postfix(tokens: &mut RegexTokenizer) -> Result<Vec<ExprUnit>, Vec<RegexError>> {
+    Ok(tokens_to_ast(tokens)?.to_postfix())
 }
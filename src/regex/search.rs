@@ -0,0 +1,954 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::{self, Read},
+    mem,
+    ops::Range,
+    sync::Arc,
+};
+
+use super::{
+    charset::CharSet,
+    config::Config,
+    enfa::{
+        concat_nfa, gen_byte_nfa, gen_class_nfa, gen_epsilon_nfa_from_expr, star_nfa, Dfa, ENfa,
+        NfaIx, Slots,
+    },
+    parsing::{parse_regex, Ast, RegexError},
+};
+
+/// A match of a regex inside a haystack, from byte offset `start` up to but
+/// not including `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match<'h> {
+    haystack: &'h str,
+    start: usize,
+    end: usize,
+}
+
+impl<'h> Match<'h> {
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Returns the matched part of the haystack.
+    pub fn as_str(&self) -> &'h str {
+        &self.haystack[self.range()]
+    }
+}
+
+/// A match of a `BytesRegex` inside a haystack of bytes, from byte offset
+/// `start` up to but not including `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BytesMatch<'h> {
+    haystack: &'h [u8],
+    start: usize,
+    end: usize,
+}
+
+impl<'h> BytesMatch<'h> {
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Returns the matched part of the haystack.
+    pub fn as_bytes(&self) -> &'h [u8] {
+        &self.haystack[self.range()]
+    }
+}
+
+/// The parts of the haystack matched by each capture group of a regex, for
+/// one match of it.
+#[derive(Debug, Clone)]
+pub struct Captures<'h> {
+    haystack: &'h str,
+    /// The start and end of each group, one after the other.
+    slots: Slots,
+    names: Arc<[Option<String>]>,
+}
+
+impl<'h> Captures<'h> {
+    /// Returns where group `i` matched, or `None` if it took no part in the
+    /// match. Group 0 is the whole match.
+    pub fn get(&self, i: usize) -> Option<Match<'h>> {
+        match (self.slots.get(2 * i)?, self.slots.get(2 * i + 1)?) {
+            (Some(start), Some(end)) => Some(Match {
+                haystack: self.haystack,
+                start: *start,
+                end: *end,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns where the group with the given name matched, or `None` if it
+    /// took no part in the match or there is no such group.
+    pub fn name(&self, name: &str) -> Option<Match<'h>> {
+        let i = self
+            .names
+            .iter()
+            .position(|other| other.as_deref() == Some(name))?;
+        self.get(i)
+    }
+}
+
+/// The DFAs for scanning forward from where a match starts to where it ends.
+///
+/// Whether an anchor holds depends on the chars around it, so a match is
+/// checked along with the char right before it, if there is one, and the
+/// char right after it. Both automata then read from that earlier char on.
+struct Forward {
+    /// Accepts the match when it ends at the end of the haystack.
+    at_end: Dfa,
+    /// Accepts the match followed by the char after it, which is how matches
+    /// ending anywhere else are told apart. Without anchors the char after a
+    /// match makes no difference, so there is none, and `at_end` is checked
+    /// one char late instead.
+    before_next: Option<Dfa>,
+}
+
+impl Forward {
+    /// Returns the DFA that tells whether the chars read so far, but the last
+    /// one, are a match.
+    fn before_next(&self) -> &Dfa {
+        self.before_next.as_ref().unwrap_or(&self.at_end)
+    }
+}
+
+/// The DFAs for matches that don't start at the start of the haystack, which
+/// read the char right before the match first.
+struct AfterChar {
+    forward: Forward,
+    /// For the earliest end of a match anywhere after the char.
+    unanchored: Forward,
+    /// Accepts the reverse of every text starting a match right after its
+    /// first char.
+    prefix: Dfa,
+}
+
+/// A compiled regex for searching haystacks, with leftmost-longest semantics:
+/// out of the matches starting the earliest, the longest one is reported.
+///
+/// Finding the next match takes three scans, which only read the haystack as
+/// far as that match needs, rather than all of it up front. A forward DFA of
+/// the regex after any string first scans ahead to the earliest position any
+/// match ends at, which is as far as `is_match` goes. The leftmost match
+/// starts no later than that, but may end later, so a DFA of the reverse of
+/// every prefix of the regex scans back from there, marking the positions
+/// where the text up to that point reads the start of a match. The forward
+/// DFAs of the regex then run from the leftmost of them to find where the
+/// match ends. Should no match start there after all, they run from all of
+/// the others at once instead, and the leftmost-longest match they find is
+/// the one reported.
+///
+/// All of the DFAs are built and minimized up front, so that searching never
+/// builds any. That makes `Regex::new` the costly part: the DFA after any
+/// string tracks every way a match could have started so far, so for a
+/// pattern like `a{1000}` it takes time quadratic in the pattern. A regex
+/// with anchors needs 10 DFAs, to check the chars around a match, and any
+/// other needs 3.
+pub struct Regex {
+    /// For matches at the start of the haystack, or anywhere if there are no
+    /// anchors.
+    forward_at_start: Forward,
+    /// For the earliest end of a match anywhere from the start of the
+    /// haystack, or from anywhere if there are no anchors.
+    unanchored_at_start: Forward,
+    /// Accepts the reverse of every text starting a match at its start, or
+    /// anywhere if there are no anchors.
+    prefix_at_start: Dfa,
+    /// For matches anywhere else, if there are anchors.
+    after_char: Option<AfterChar>,
+    /// The ε-NFA with tagged transitions, for finding the captures of a match
+    /// once it is found.
+    enfa: ENfa,
+    /// The name of each capture group, by group number.
+    capture_names: Arc<[Option<String>]>,
+    /// One past the last group nested inside each capture group, as in
+    /// `Ast::nested_captures`.
+    nested_captures: Vec<usize>,
+}
+
+/// Returns the minimal DFA of `enfa`, laid out for scanning.
+fn to_dfa(enfa: ENfa) -> Dfa {
+    let mut nfa = enfa.to_nfa();
+    nfa.remove_unreachable_nodes();
+    nfa.minimized_dfa().to_dfa()
+}
+
+/// Returns the minimal DFA of the reverse of every prefix of a string `enfa`
+/// accepts, laid out for scanning.
+fn to_reverse_prefix_dfa(enfa: ENfa) -> Dfa {
+    let mut nfa = enfa.to_nfa();
+    nfa.remove_unreachable_nodes();
+    nfa.prefixes().reverse().minimized_dfa().to_dfa()
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Regex, Vec<RegexError>> {
+        Regex::with_config(pattern, &Config::default())
+    }
+
+    pub fn with_config(pattern: &str, config: &Config) -> Result<Regex, Vec<RegexError>> {
+        let ast = parse_regex(pattern, config)?;
+        let enfa = gen_epsilon_nfa_from_expr(&ast, config).map_err(|err| vec![err])?;
+        let any_char = || gen_class_nfa(CharSet::full());
+        Ok(Regex::build(enfa, any_char, &ast))
+    }
+
+    /// Builds the DFAs for searching with `enfa`, the ε-NFA of `ast`, where
+    /// `any_unit` reads any single unit of the haystack, a char or a byte,
+    /// like `enfa` does.
+    fn build(enfa: ENfa, any_unit: fn() -> ENfa, ast: &Ast) -> Regex {
+        let any_string = || {
+            let mut nfa = any_unit();
+            star_nfa(&mut nfa);
+            nfa
+        };
+        let looks = enfa.has_looks();
+        let unanchored = concat_nfa(any_string(), enfa.clone());
+
+        let forward = |enfa: ENfa| Forward {
+            at_end: to_dfa(enfa.clone()),
+            before_next: looks.then(|| to_dfa(concat_nfa(enfa, any_unit()))),
+        };
+        let after_char = looks.then(|| {
+            let after_char = concat_nfa(any_unit(), enfa.clone());
+            AfterChar {
+                forward: forward(after_char.clone()),
+                unanchored: forward(concat_nfa(any_unit(), unanchored.clone())),
+                prefix: to_reverse_prefix_dfa(concat_nfa(after_char, any_string())),
+            }
+        });
+        Regex {
+            forward_at_start: forward(enfa.clone()),
+            unanchored_at_start: forward(unanchored),
+            prefix_at_start: to_reverse_prefix_dfa(concat_nfa(enfa.clone(), any_string())),
+            after_char,
+            enfa,
+            capture_names: ast.capture_names().into(),
+            nested_captures: ast.nested_captures(),
+        }
+    }
+
+    /// Returns the number of capture groups, counting the whole match as
+    /// group 0.
+    pub fn captures_len(&self) -> usize {
+        self.capture_names.len()
+    }
+
+    /// Returns the name of each capture group by group number, or `None` for
+    /// unnamed groups.
+    pub fn capture_names(&self) -> impl Iterator<Item = Option<&str>> {
+        self.capture_names.iter().map(|name| name.as_deref())
+    }
+
+    /// Returns the leftmost-longest match in `haystack` along with where each
+    /// capture group matched inside of it, if there is a match.
+    ///
+    /// When the groups could split up the match in more than one way, each
+    /// group in turn takes the leftmost-longest part it can, as in POSIX.
+    /// Groups inside a repetition report their last iteration, so a group
+    /// nested in a repeated group takes no part unless it matched in the last
+    /// iteration of the outer one, as `(a(b)?)+` on "aba" has no group 2.
+    /// Groups under `&`, `--` or `~` never take part.
+    pub fn captures<'h>(&self, haystack: &'h str) -> Option<Captures<'h>> {
+        let m = self.find(haystack)?;
+        let mut slots = self
+            .enfa
+            .capture_slots(haystack, m.start, m.end, &self.nested_captures)
+            .expect("the ε-NFA accepts every match");
+        (slots[0], slots[1]) = (Some(m.start), Some(m.end));
+        Some(Captures {
+            haystack,
+            slots,
+            names: self.capture_names.clone(),
+        })
+    }
+
+    /// Returns whether there is a match anywhere in `haystack`.
+    pub fn is_match(&self, haystack: &str) -> bool {
+        self.earliest_end(haystack, 0).is_some()
+    }
+
+    /// Returns the leftmost-longest match in `haystack`, if there is one.
+    pub fn find<'h>(&self, haystack: &'h str) -> Option<Match<'h>> {
+        self.find_iter(haystack).next()
+    }
+
+    /// Returns every leftmost-longest match in `haystack`, from front to
+    /// back. Matches never overlap, and an empty match right where the
+    /// previous match ends is skipped.
+    pub fn find_iter<'r, 'h>(&'r self, haystack: &'h str) -> Matches<'r, 'h> {
+        Matches {
+            search: Search::new(self, haystack),
+        }
+    }
+
+    /// Returns a matcher for searching text that is fed to it a chunk at a
+    /// time, rather than held in a single `&str`.
+    pub fn stream(&self) -> StreamMatcher<'_> {
+        StreamMatcher {
+            regex: self,
+            buffer: VecDeque::new(),
+            buffer_start: 0,
+            pos: 0,
+            len: 0,
+            runs: Runs::default(),
+            search_from: 0,
+            last_end: None,
+            partial: Vec::new(),
+        }
+    }
+
+    fn forward(&self, after_char: bool, unanchored: bool) -> &Forward {
+        match (&self.after_char, after_char, unanchored) {
+            (Some(after_char), true, false) => &after_char.forward,
+            (Some(after_char), true, true) => &after_char.unanchored,
+            (_, _, false) => &self.forward_at_start,
+            (_, _, true) => &self.unanchored_at_start,
+        }
+    }
+
+    /// Returns the earliest offset a match starting at offset `from` or later
+    /// ends at, if there is one.
+    fn earliest_end<H: Haystack + ?Sized>(&self, haystack: &H, from: usize) -> Option<usize> {
+        let before = haystack.unit_before(from).map(|(c, _)| c);
+        let mut run = Run::new(self, before, true);
+        let mut at = from;
+        while let Some((c, len)) = haystack.unit_after(at) {
+            if run.is_dead(self) {
+                return None;
+            }
+            run = run.step(self, c);
+            if run.accepts_before_next(self) {
+                return Some(at);
+            }
+            at += len;
+        }
+        run.accepts_at_end(self).then_some(at)
+    }
+
+    /// Returns, in order, the offsets from `from` up to `end` where the text
+    /// up to `end` reads the start of a match.
+    fn match_prefixes<H: Haystack + ?Sized>(
+        &self,
+        haystack: &H,
+        from: usize,
+        end: usize,
+    ) -> Vec<usize> {
+        let mut starts = Vec::new();
+        let prefix_after_char = self
+            .after_char
+            .as_ref()
+            .map(|after_char| &after_char.prefix);
+        let mut at_start = self.prefix_at_start.start();
+        let mut after_char = prefix_after_char.map(Dfa::start);
+        let mut at = end;
+        loop {
+            let Some((c, len)) = haystack.unit_before(at) else {
+                if self.prefix_at_start.is_final(at_start) {
+                    starts.push(at);
+                }
+                break;
+            };
+            // Having read back to the char before `at`.
+            let starts_here = match (prefix_after_char, after_char.as_mut()) {
+                (Some(prefix), Some(state)) => {
+                    *state = prefix.next_state(*state, c);
+                    prefix.is_final(*state)
+                }
+                // Without anchors a match starts the same after any char.
+                _ => self.prefix_at_start.is_final(at_start),
+            };
+            if starts_here {
+                starts.push(at);
+            }
+            at_start = self.prefix_at_start.next_state(at_start, c);
+            let after_char_dead = match (prefix_after_char, after_char) {
+                (Some(prefix), Some(state)) => prefix.is_dead(state),
+                _ => true,
+            };
+            if at == from || after_char_dead && self.prefix_at_start.is_dead(at_start) {
+                break;
+            }
+            at -= len;
+        }
+        starts.reverse();
+        starts
+    }
+
+    /// Returns the offsets of the leftmost-longest match starting at offset
+    /// `from` or later, if there is one.
+    fn find_at<H: Haystack + ?Sized>(&self, haystack: &H, from: usize) -> Option<Range<usize>> {
+        let earliest_end = self.earliest_end(haystack, from)?;
+        // Any match starting anywhere else would end before `earliest_end`.
+        let starts = self.match_prefixes(haystack, from, earliest_end);
+        // The leftmost of them most often starts a match, and then no other
+        // one needs to be tried.
+        let (&first, rest) = starts
+            .split_first()
+            .expect("the earliest match starts somewhere");
+        if let Some(end) = self.longest_end(haystack, first) {
+            return Some(first..end);
+        }
+        let mut starts = rest.iter().copied().peekable();
+        let mut runs = Runs::default();
+        let mut at = *starts.peek()?;
+        loop {
+            if starts.next_if_eq(&at).is_some() {
+                let before = haystack.unit_before(at).map(|(c, _)| c);
+                runs.start(self, at, before);
+            }
+            if let Some((start, end)) = runs.settled() {
+                return Some(start..end);
+            }
+            match haystack.unit_after(at) {
+                Some((c, len)) => {
+                    runs.step(self, at, c);
+                    at += len;
+                }
+                None => {
+                    runs.end(self, at);
+                    return runs.settled().map(|(start, end)| start..end);
+                }
+            }
+        }
+    }
+
+    /// Returns the end of the longest match starting at offset `start`, if
+    /// there is one.
+    fn longest_end<H: Haystack + ?Sized>(&self, haystack: &H, start: usize) -> Option<usize> {
+        let before = haystack.unit_before(start).map(|(c, _)| c);
+        let mut run = Run::new(self, before, false);
+        let mut end = None;
+        let mut at = start;
+        while let Some((c, len)) = haystack.unit_after(at) {
+            if run.is_dead(self) {
+                return end;
+            }
+            run = run.step(self, c);
+            if run.accepts_before_next(self) {
+                end = Some(at);
+            }
+            at += len;
+        }
+        if run.accepts_at_end(self) {
+            end = Some(at);
+        }
+        end
+    }
+}
+
+/// A haystack read a unit at a time, the chars of a `str` or the bytes of a
+/// `[u8]`. Bytes are read as the chars with the same value, as in
+/// `CharSet::from_bytes`.
+trait Haystack {
+    /// Returns the unit starting at byte offset `at`, along with its length
+    /// in bytes, or `None` at the end of the haystack.
+    fn unit_after(&self, at: usize) -> Option<(char, usize)>;
+
+    /// Returns the unit ending at byte offset `at`, along with its length in
+    /// bytes, or `None` at the start of the haystack.
+    fn unit_before(&self, at: usize) -> Option<(char, usize)>;
+}
+
+impl Haystack for str {
+    fn unit_after(&self, at: usize) -> Option<(char, usize)> {
+        let c = self[at..].chars().next()?;
+        Some((c, c.len_utf8()))
+    }
+
+    fn unit_before(&self, at: usize) -> Option<(char, usize)> {
+        let c = self[..at].chars().next_back()?;
+        Some((c, c.len_utf8()))
+    }
+}
+
+impl Haystack for [u8] {
+    fn unit_after(&self, at: usize) -> Option<(char, usize)> {
+        self.get(at).map(|&b| (char::from(b), 1))
+    }
+
+    fn unit_before(&self, at: usize) -> Option<(char, usize)> {
+        let b = self[..at].last()?;
+        Some((char::from(*b), 1))
+    }
+}
+
+/// A search for one match after another in a haystack.
+struct Search<'r, 'h, H: ?Sized> {
+    regex: &'r Regex,
+    haystack: &'h H,
+    /// The offset to look for the next match from, or `None` once there are
+    /// no more.
+    pos: Option<usize>,
+    /// The offset the previous match ended at.
+    last_end: Option<usize>,
+}
+
+impl<'r, 'h, H: Haystack + ?Sized> Search<'r, 'h, H> {
+    fn new(regex: &'r Regex, haystack: &'h H) -> Self {
+        Search {
+            regex,
+            haystack,
+            pos: Some(0),
+            last_end: None,
+        }
+    }
+
+    /// Returns the byte offsets of the next match, if there is one.
+    fn next_match(&mut self) -> Option<Range<usize>> {
+        loop {
+            let found = self.regex.find_at(self.haystack, self.pos?);
+            let Some(Range { start, end }) = found else {
+                self.pos = None;
+                return None;
+            };
+            self.pos = if end == start {
+                self.haystack.unit_after(start).map(|(_, len)| start + len)
+            } else {
+                Some(end)
+            };
+            if end == start && self.last_end == Some(start) {
+                continue;
+            }
+            self.last_end = Some(end);
+            return Some(start..end);
+        }
+    }
+}
+
+/// An iterator over the matches of a regex in a haystack, returned by
+/// `Regex::find_iter`.
+pub struct Matches<'r, 'h> {
+    search: Search<'r, 'h, str>,
+}
+
+impl<'r, 'h> Iterator for Matches<'r, 'h> {
+    type Item = Match<'h>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let range = self.search.next_match()?;
+        Some(Match {
+            haystack: self.search.haystack,
+            start: range.start,
+            end: range.end,
+        })
+    }
+}
+
+/// A compiled regex for searching haystacks of bytes, which don't have to be
+/// valid UTF-8, with the same leftmost-longest semantics as `Regex`.
+///
+/// The pattern is compiled with `ENfa::to_utf8`, so its chars match their
+/// UTF-8 encodings, and classes like `.` or `\w` only match whole, valid
+/// encodings. In between matches, the search steps over any byte at all.
+/// Empty matches can fall inside an encoding though, eg. `\e` matches between
+/// the two bytes of "é".
+pub struct BytesRegex {
+    regex: Regex,
+}
+
+impl BytesRegex {
+    pub fn new(pattern: &str) -> Result<BytesRegex, Vec<RegexError>> {
+        BytesRegex::with_config(pattern, &Config::default())
+    }
+
+    pub fn with_config(pattern: &str, config: &Config) -> Result<BytesRegex, Vec<RegexError>> {
+        let ast = parse_regex(pattern, config)?;
+        let enfa = gen_epsilon_nfa_from_expr(&ast, config).map_err(|err| vec![err])?;
+        let any_byte = || gen_byte_nfa(0, 0xFF);
+        Ok(BytesRegex {
+            regex: Regex::build(enfa.to_utf8(), any_byte, &ast),
+        })
+    }
+
+    /// Returns whether there is a match anywhere in `haystack`.
+    pub fn is_match(&self, haystack: &[u8]) -> bool {
+        self.regex.earliest_end(haystack, 0).is_some()
+    }
+
+    /// Returns the leftmost-longest match in `haystack`, if there is one.
+    pub fn find<'h>(&self, haystack: &'h [u8]) -> Option<BytesMatch<'h>> {
+        self.find_iter(haystack).next()
+    }
+
+    /// Returns every leftmost-longest match in `haystack`, from front to
+    /// back, as with `Regex::find_iter`.
+    pub fn find_iter<'r, 'h>(&'r self, haystack: &'h [u8]) -> BytesMatches<'r, 'h> {
+        BytesMatches {
+            search: Search::new(&self.regex, haystack),
+        }
+    }
+}
+
+/// An iterator over the matches of a regex in a haystack of bytes, returned
+/// by `BytesRegex::find_iter`.
+pub struct BytesMatches<'r, 'h> {
+    search: Search<'r, 'h, [u8]>,
+}
+
+impl<'r, 'h> Iterator for BytesMatches<'r, 'h> {
+    type Item = BytesMatch<'h>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let range = self.search.next_match()?;
+        Some(BytesMatch {
+            haystack: self.search.haystack,
+            start: range.start,
+            end: range.end,
+        })
+    }
+}
+
+/// The states of one pair of forward DFAs for a match starting at some
+/// position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Run {
+    /// Whether the run reads the char before the match first.
+    after_char: bool,
+    /// Whether the run is for a match starting anywhere from its position on,
+    /// rather than right at it.
+    unanchored: bool,
+    at_end: NfaIx,
+    /// The state of `Forward::before_next`, which without anchors is the
+    /// state `at_end` was in before the last char.
+    before_next: NfaIx,
+}
+
+impl Run {
+    /// Returns the run for a match starting right after the char `before`,
+    /// or at the start of the text if there is none.
+    fn new(regex: &Regex, before: Option<char>, unanchored: bool) -> Run {
+        // Without anchors the char before the match makes no difference.
+        let before = before.filter(|_| regex.after_char.is_some());
+        let forward = regex.forward(before.is_some(), unanchored);
+        let run = Run {
+            after_char: before.is_some(),
+            unanchored,
+            at_end: forward.at_end.start(),
+            before_next: match &forward.before_next {
+                Some(before_next) => before_next.start(),
+                // No chars were read, so none of them are a match yet.
+                None => forward.at_end.dead_state(),
+            },
+        };
+        match before {
+            Some(c) => run.step(regex, c),
+            None => run,
+        }
+    }
+
+    fn step(self, regex: &Regex, c: char) -> Run {
+        let forward = self.forward(regex);
+        Run {
+            at_end: forward.at_end.next_state(self.at_end, c),
+            before_next: match &forward.before_next {
+                Some(before_next) => before_next.next_state(self.before_next, c),
+                None => self.at_end,
+            },
+            ..self
+        }
+    }
+
+    fn forward(self, regex: &Regex) -> &Forward {
+        regex.forward(self.after_char, self.unanchored)
+    }
+
+    /// Returns whether the chars read so far, but the last one, are a match.
+    fn accepts_before_next(&self, regex: &Regex) -> bool {
+        self.forward(regex).before_next().is_final(self.before_next)
+    }
+
+    /// Returns whether the chars read so far are a match at the end of the
+    /// text.
+    fn accepts_at_end(&self, regex: &Regex) -> bool {
+        self.forward(regex).at_end.is_final(self.at_end)
+    }
+
+    fn is_dead(&self, regex: &Regex) -> bool {
+        let forward = self.forward(regex);
+        forward.at_end.is_dead(self.at_end) && forward.before_next().is_dead(self.before_next)
+    }
+}
+
+/// The forward runs for matches starting at many positions at once, which
+/// look for the leftmost-longest of them.
+///
+/// Runs that reach the same states are merged, keeping the one that started
+/// earliest, so there are never more runs than pairs of states. Once a match
+/// is found, runs starting after it are dropped, and the match is settled
+/// when no run that could still find an earlier or longer one is left.
+#[derive(Debug, Default)]
+struct Runs {
+    /// The live runs, with the position each one started at.
+    runs: BTreeMap<Run, usize>,
+    /// The leftmost-longest match found so far.
+    best: Option<(usize, usize)>,
+}
+
+impl Runs {
+    /// Starts a run for a match at `pos`, right after the char `before`,
+    /// unless one was already found earlier.
+    fn start(&mut self, regex: &Regex, pos: usize, before: Option<char>) {
+        if self.best.is_some() {
+            return;
+        }
+        let run = Run::new(regex, before, false);
+        if !run.is_dead(regex) {
+            // A run already in the same states started earlier.
+            self.runs.entry(run).or_insert(pos);
+        }
+    }
+
+    /// Moves every run past the char `c` at `pos`.
+    fn step(&mut self, regex: &Regex, pos: usize, c: char) {
+        for (run, start) in mem::take(&mut self.runs) {
+            let next = run.step(regex, c);
+            if next.accepts_before_next(regex) {
+                self.found(start, pos);
+            }
+            if !next.is_dead(regex) {
+                let earliest = self.runs.entry(next).or_insert(start);
+                *earliest = (*earliest).min(start);
+            }
+        }
+        if let Some((best_start, _)) = self.best {
+            self.runs.retain(|_, start| *start <= best_start);
+        }
+    }
+
+    /// Ends every run at the end of the text, at `pos`.
+    fn end(&mut self, regex: &Regex, pos: usize) {
+        for (run, start) in mem::take(&mut self.runs) {
+            if run.accepts_at_end(regex) {
+                self.found(start, pos);
+            }
+        }
+    }
+
+    fn found(&mut self, start: usize, end: usize) {
+        match self.best {
+            Some((best_start, best_end)) if (best_start, end) < (start, best_end) => {}
+            _ => self.best = Some((start, end)),
+        }
+    }
+
+    /// Returns the best match once no run can find a better one.
+    fn settled(&self) -> Option<(usize, usize)> {
+        let (start, end) = self.best?;
+        let better_left = self.runs.values().any(|&other| other <= start);
+        (!better_left).then_some((start, end))
+    }
+
+    /// Returns the earliest position a run or the best match starts at.
+    fn earliest(&self) -> Option<usize> {
+        let best = self.best.as_ref().map(|(start, _)| start);
+        self.runs.values().chain(best).copied().min()
+    }
+}
+
+/// Searches a text that is fed to it a chunk at a time, returned by
+/// `Regex::stream`. It finds the same matches as `Regex::find_iter` would on
+/// the whole text, with byte offsets counted from the start of the text.
+///
+/// Where a match could start is only known once the text after it is read, so
+/// the forward DFAs are run from every position at once, as `Runs`. Only the
+/// text from the start of the earliest run on is kept, since the search picks
+/// up again from the end of the match.
+pub struct StreamMatcher<'r> {
+    regex: &'r Regex,
+    /// The chars read and still needed, with their byte offsets.
+    buffer: VecDeque<(usize, char)>,
+    /// The char position of the front of the buffer.
+    buffer_start: usize,
+    /// The char position to scan from next.
+    pos: usize,
+    /// The number of bytes read so far.
+    len: usize,
+    /// The live runs, by char position.
+    runs: Runs,
+    /// The char position the next match can start at the earliest.
+    search_from: usize,
+    /// The char position the previous match ended at.
+    last_end: Option<usize>,
+    /// The start of a UTF-8 sequence cut off at the end of the last chunk.
+    partial: Vec<u8>,
+}
+
+impl<'r> StreamMatcher<'r> {
+    /// Reads the next chunk of text, and returns the matches found so far.
+    pub fn feed_str(&mut self, chunk: &str) -> Vec<Range<usize>> {
+        self.feed_chars(chunk.chars())
+    }
+
+    /// Reads the next chunk of text as chars, and returns the matches found
+    /// so far.
+    pub fn feed_chars<I: IntoIterator<Item = char>>(&mut self, chunk: I) -> Vec<Range<usize>> {
+        for c in chunk {
+            self.push(c, c.len_utf8());
+        }
+        let mut matches = Vec::new();
+        self.scan(&mut matches);
+        matches
+    }
+
+    /// Reads the next chunk of text as UTF-8, and returns the matches found
+    /// so far. A chunk can end in the middle of a char, which is then
+    /// completed by the next one. Invalid UTF-8 is read as U+FFFD, the
+    /// replacement char, one for each invalid sequence.
+    pub fn feed_bytes(&mut self, chunk: &[u8]) -> Vec<Range<usize>> {
+        let mut bytes = mem::take(&mut self.partial);
+        bytes.extend_from_slice(chunk);
+        let mut rest = &bytes[..];
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(valid) => {
+                    self.push_str(valid);
+                    break;
+                }
+                Err(err) => {
+                    let (valid, invalid) = rest.split_at(err.valid_up_to());
+                    self.push_str(std::str::from_utf8(valid).expect("checked to be valid"));
+                    match err.error_len() {
+                        Some(len) => {
+                            self.push(char::REPLACEMENT_CHARACTER, len);
+                            rest = &invalid[len..];
+                        }
+                        // The sequence may go on in the next chunk.
+                        None => {
+                            self.partial = invalid.to_vec();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        let mut matches = Vec::new();
+        self.scan(&mut matches);
+        matches
+    }
+
+    /// Reads `reader` to the end as UTF-8, and returns the matches found so
+    /// far.
+    pub fn feed_reader<R: Read>(&mut self, mut reader: R) -> io::Result<Vec<Range<usize>>> {
+        let mut chunk = [0; 8192];
+        let mut matches = Vec::new();
+        loop {
+            match reader.read(&mut chunk) {
+                Ok(0) => return Ok(matches),
+                Ok(n) => matches.extend(self.feed_bytes(&chunk[..n])),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Ends the text, and returns the matches that were still waiting on
+    /// what came next.
+    pub fn finish(mut self) -> Vec<Range<usize>> {
+        if !self.partial.is_empty() {
+            self.push(char::REPLACEMENT_CHARACTER, self.partial.len());
+        }
+        let mut matches = Vec::new();
+        loop {
+            self.scan(&mut matches);
+            self.start_run();
+            self.runs.end(self.regex, self.pos);
+            if !self.report(&mut matches) {
+                return matches;
+            }
+        }
+    }
+
+    fn push(&mut self, c: char, len: usize) {
+        self.buffer.push_back((self.len, c));
+        self.len += len;
+    }
+
+    fn push_str(&mut self, s: &str) {
+        for c in s.chars() {
+            self.push(c, c.len_utf8());
+        }
+    }
+
+    fn offset(&self, pos: usize) -> usize {
+        self.buffer
+            .get(pos - self.buffer_start)
+            .map_or(self.len, |&(offset, _)| offset)
+    }
+
+    /// Runs every char in the buffer from `pos` on.
+    fn scan(&mut self, matches: &mut Vec<Range<usize>>) {
+        while let Some(&(_, c)) = self.buffer.get(self.pos - self.buffer_start) {
+            self.start_run();
+            self.runs.step(self.regex, self.pos, c);
+            self.pos += 1;
+            self.report(matches);
+        }
+        // Keep the char before the earliest position still needed, for
+        // context.
+        let needed = self
+            .runs
+            .earliest()
+            .map_or(self.pos, |start| start.min(self.pos));
+        while self.buffer_start + 1 < needed {
+            self.buffer.pop_front();
+            self.buffer_start += 1;
+        }
+    }
+
+    /// Starts a run for a match at `pos`, unless one was already found
+    /// earlier.
+    fn start_run(&mut self) {
+        let text_end = self.buffer_start + self.buffer.len();
+        if self.pos < self.search_from || self.pos > text_end {
+            return;
+        }
+        let before = match self.pos {
+            0 => None,
+            _ => Some(self.buffer[self.pos - 1 - self.buffer_start].1),
+        };
+        self.runs.start(self.regex, self.pos, before);
+    }
+
+    /// Reports the best match once no run can find a better one, and goes
+    /// back to search on from its end. Returns whether there was one.
+    fn report(&mut self, matches: &mut Vec<Range<usize>>) -> bool {
+        let Some((start, end)) = self.runs.settled() else {
+            return false;
+        };
+        self.runs = Runs::default();
+        self.search_from = if end == start { start + 1 } else { end };
+        self.pos = self.search_from;
+        if !(end == start && self.last_end == Some(start)) {
+            matches.push(self.offset(start)..self.offset(end));
+            self.last_end = Some(end);
+        }
+        true
+    }
+}
@@ -0,0 +1,89 @@
+use super::{
+    config::Config,
+    enfa::{gen_epsilon_nfa_from_expr, union_all_dfa, Dfa},
+    parsing::{parse_regex, RegexError},
+};
+
+/// A set of regexes, searched for all at once. This answers which of the
+/// patterns have a match somewhere in a haystack, in a single pass over it.
+///
+/// The unanchored ε-NFAs of the patterns are joined under a shared start
+/// state, keeping their final states apart, and determinized once with the
+/// subset construction. Each state of the DFA is then tagged with the
+/// patterns whose final states are among the ones it stands for. A pattern
+/// with a match stays accepted up to the end of the haystack, thanks to the
+/// `[^]*` loop after it, so the state the DFA ends in has every pattern with
+/// a match.
+#[derive(Debug, Clone)]
+pub struct RegexSet {
+    dfa: Dfa,
+    /// The patterns accepted in each state of the DFA, in order.
+    accepts: Vec<Vec<usize>>,
+    len: usize,
+}
+
+impl RegexSet {
+    /// Compiles the patterns into a set. On failure, returns the index of the
+    /// first pattern that didn't compile along with its errors.
+    pub fn new<I, S>(patterns: I) -> Result<RegexSet, (usize, Vec<RegexError>)>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        RegexSet::with_config(patterns, &Config::default())
+    }
+
+    pub fn with_config<I, S>(
+        patterns: I,
+        config: &Config,
+    ) -> Result<RegexSet, (usize, Vec<RegexError>)>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut enfas = vec![];
+        for (i, pattern) in patterns.into_iter().enumerate() {
+            let ast = parse_regex(pattern.as_ref(), config).map_err(|errs| (i, errs))?;
+            let enfa = gen_epsilon_nfa_from_expr(&ast, config).map_err(|err| (i, vec![err]))?;
+            enfas.push(enfa.unanchored());
+        }
+        let len = enfas.len();
+
+        let (dfa, accepts) = union_all_dfa(enfas);
+
+        Ok(RegexSet {
+            dfa: dfa.to_dfa(),
+            accepts,
+            len,
+        })
+    }
+
+    /// Returns the number of patterns in the set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the indices of the patterns with a match somewhere in
+    /// `haystack`, in increasing order.
+    pub fn matches(&self, haystack: &str) -> Vec<usize> {
+        let mut state = self.dfa.start();
+        for c in haystack.chars() {
+            state = self.dfa.next_state(state, c);
+            // None of the patterns can match anymore, as with `^a` once the
+            // haystack starts otherwise.
+            if self.dfa.is_dead(state) {
+                return vec![];
+            }
+        }
+        self.accepts[usize::try_from(state).unwrap()].clone()
+    }
+
+    /// Returns whether any of the patterns has a match in `haystack`.
+    pub fn is_match(&self, haystack: &str) -> bool {
+        !self.matches(haystack).is_empty()
+    }
+}
@@ -1,42 +1,589 @@
 use std::str::Chars;
 
-#[derive(Debug, Clone, Copy)]
+use super::{
+    charset::CharSet,
+    config::Config,
+    parsing::{Fix, Flag, RegexError, RegexErrorKind, RepetitionError, Span},
+    unicode_tables::{self, perl_decimal, perl_space, perl_word},
+};
+
+#[derive(Debug, Clone)]
 pub enum Token {
     Char(char),
+    Class(CharSet),
+    Dot,
+    /// The empty string, `\e`.
+    Epsilon,
+    /// Start of text or line, `^`.
+    Caret,
+    /// End of text or line, `$`.
+    Dollar,
     Star,
+    Plus,
+    Question,
+    Repeat {
+        min: u32,
+        max: Option<u32>,
+    },
     Pipe,
+    /// Intersection, `&`.
+    Ampersand,
+    /// Difference, `--`.
+    DoubleDash,
+    /// Complement, `~`.
+    Tilde,
     OpenParens,
+    /// The start of a named group, `(?<name>`.
+    OpenNamedGroup(String),
+    /// The start of a group that doesn't capture, `(?:`, or of one turning
+    /// flags on or off inside of it, as in `(?i:`.
+    OpenFlagGroup(Vec<(Flag, bool)>),
     CloseParens,
-    Backslash,
+    /// A flag group like `(?i)` or `(?-i)`, turning each flag on or off.
+    SetFlags(Vec<(Flag, bool)>),
+}
+
+/// Returns the set for the shorthand class `\<name>`, one of `\d`, `\w` and
+/// `\s`, or for the lowercase one of their negations `\D`, `\W` and `\S`.
+fn perl_class(name: char, unicode: bool) -> Option<CharSet> {
+    let set = match (name.to_ascii_lowercase(), unicode) {
+        ('d', false) => CharSet::from_ranges([('0', '9')]),
+        ('w', false) => CharSet::from_ranges([('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')]),
+        ('s', false) => CharSet::from_ranges([('\t', '\r'), (' ', ' ')]),
+        ('d', true) => CharSet::from_ranges(perl_decimal::DECIMAL_NUMBER.iter().copied()),
+        ('w', true) => CharSet::from_ranges(perl_word::PERL_WORD.iter().copied()),
+        ('s', true) => CharSet::from_ranges(perl_space::WHITE_SPACE.iter().copied()),
+        _ => return None,
+    };
+    Some(set)
+}
+
+/// Returns the char with the code point written as `digits` in hex, if there
+/// are any digits and they name a Unicode scalar value.
+fn code_point(digits: &str) -> Option<char> {
+    let significant = digits.trim_start_matches('0');
+    if digits.is_empty() || significant.len() > 6 {
+        return None;
+    }
+    // Only zeros leave no significant digits.
+    char::from_u32(u32::from_str_radix(significant, 16).unwrap_or(0))
 }
 
 pub struct RegexTokenizer<'a> {
+    string: &'a str,
     char_iter: Chars<'a>,
+    config: Config,
+    /// Whether the tokens being read are to match regardless of case, as set
+    /// by the parser from the flags in effect.
+    case_insensitive: bool,
+    /// An error to report right after the token just read, for errors which
+    /// don't stop the token from being read.
+    deferred: Option<RegexError>,
 }
 
 impl<'a> RegexTokenizer<'a> {
     pub fn from_string(string: &'a str) -> RegexTokenizer<'a> {
-        return RegexTokenizer {
+        RegexTokenizer::with_config(string, Config::default())
+    }
+
+    pub fn with_config(string: &'a str, config: Config) -> RegexTokenizer<'a> {
+        RegexTokenizer {
+            string,
             char_iter: string.chars(),
+            case_insensitive: config.get_case_insensitive(),
+            config,
+            deferred: None,
+        }
+    }
+
+    /// Returns the byte offset of the next char to be read.
+    pub fn offset(&self) -> usize {
+        self.string.len() - self.char_iter.as_str().len()
+    }
+
+    pub fn pattern(&self) -> &'a str {
+        self.string
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Sets whether the tokens read from here on are to match regardless of
+    /// case.
+    pub fn set_case_insensitive(&mut self, yes: bool) {
+        self.case_insensitive = yes;
+    }
+
+    /// Returns every char not in `set`. When matching regardless of case, the
+    /// set gets its case variants first, so that `(?i)[^k]` matches neither
+    /// `k` nor `K`. Folding after negating would instead bring them back.
+    fn negate(&self, set: CharSet) -> CharSet {
+        if self.case_insensitive {
+            set.case_fold().negate()
+        } else {
+            set.negate()
+        }
+    }
+
+    /// Builds an error about the part of the pattern from `start` up to the
+    /// next char to be read.
+    fn error(&self, kind: RegexErrorKind, start: usize) -> RegexError {
+        RegexError::new(kind, Span::new(start, self.offset()))
+    }
+
+    /// Builds a fix replacing the part of the pattern at `span`.
+    fn fix(&self, span: Span, replacement: &str) -> Fix {
+        Fix::new(span, &self.string[span.start..span.end], replacement)
+    }
+
+    /// Builds a fix inserting `text` right before the next char to be read.
+    fn insert_here(&self, text: &str) -> Fix {
+        Fix::insert(self.offset(), text)
+    }
+
+    /// Reads an escape standing for a set of chars if one comes next, the
+    /// leading backslash having already been consumed. These are the
+    /// shorthand classes like `\d`, and Unicode properties like `\p{Greek}`.
+    fn class_escape(&mut self) -> Result<Option<CharSet>, RegexError> {
+        let start = self.offset() - 1;
+        let name = match self.char_iter.clone().next() {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        if let Some(set) = perl_class(name, self.config.get_unicode()) {
+            self.char_iter.next();
+            return Ok(Some(if name.is_ascii_uppercase() {
+                self.negate(set)
+            } else {
+                set
+            }));
+        }
+        if name != 'p' && name != 'P' {
+            return Ok(None);
+        }
+        self.char_iter.next();
+
+        // Either a single letter, as in `\pL`, or a name in braces.
+        let (property, name_start) = match self.char_iter.next() {
+            Some('{') => {
+                let name_start = self.offset();
+                let mut property = String::new();
+                loop {
+                    match self.char_iter.next() {
+                        Some('}') => break,
+                        Some(c) => property.push(c),
+                        None => {
+                            return Err(self
+                                .error(RegexErrorKind::UnclosedProperty, start)
+                                .with_fix(self.insert_here("}")))
+                        }
+                    }
+                }
+                (property, name_start)
+            }
+            Some(c) => (c.to_string(), self.offset() - c.len_utf8()),
+            None => {
+                let err = self.error(RegexErrorKind::TrailingProperty, start);
+                let fix = self.fix(err.span, "");
+                return Err(err.with_fix(fix));
+            }
+        };
+
+        let set = match unicode_tables::property_set(&property) {
+            Some(set) => set,
+            None => {
+                let err = self.error(RegexErrorKind::UnknownProperty(property.clone()), start);
+                // Likely a misspelling of a known property.
+                return Err(match unicode_tables::suggest_property(&property) {
+                    Some(suggestion) => {
+                        let name = Span::new(name_start, name_start + property.len());
+                        let fix = self.fix(name, &suggestion);
+                        err.with_fix(fix)
+                    }
+                    None => err,
+                });
+            }
         };
+        Ok(Some(if name == 'P' { self.negate(set) } else { set }))
     }
-}
 
-impl<'a> Iterator for RegexTokenizer<'a> {
-    type Item = Token;
-
-    fn next(&mut self) -> Option<Token> {
-        if let Some(c) = self.char_iter.next() {
-            match c {
-                '*' => Some(Token::Star),
-                '|' => Some(Token::Pipe),
-                '(' => Some(Token::OpenParens),
-                ')' => Some(Token::CloseParens),
-                '\\' => Some(Token::Backslash),
-                _ => Some(Token::Char(c)),
+    /// Reads the rest of an escape sequence, the leading backslash having
+    /// already been consumed.
+    fn escape(&mut self) -> Result<Token, RegexError> {
+        if self.char_iter.clone().next() == Some('e') {
+            self.char_iter.next();
+            return Ok(Token::Epsilon);
+        }
+        if let Some(set) = self.class_escape()? {
+            return Ok(Token::Class(set));
+        }
+        self.escaped_char().map(Token::Char)
+    }
+
+    /// Reads the rest of an escape sequence standing for a single char.
+    fn escaped_char(&mut self) -> Result<char, RegexError> {
+        let start = self.offset() - 1;
+        let c = self.char_iter.next().ok_or_else(|| {
+            self.error(RegexErrorKind::TrailingBackslash, start)
+                .with_fix(self.insert_here("\\"))
+        })?;
+        match c {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            'u' => self.unicode_escape(start),
+            // Any ASCII punctuation may be escaped to stand for itself, so
+            // that metacharacters can be matched literally.
+            c if c.is_ascii_punctuation() => Ok(c),
+            c => Err(self
+                .error(RegexErrorKind::UnknownEscape(c), start)
+                .with_fix(self.fix(Span::new(start, start + 1), ""))),
+        }
+    }
+
+    /// Reads a single member of a bracket class, which is either a plain char
+    /// or an escape.
+    fn class_char(&mut self, start: usize) -> Result<char, RegexError> {
+        match self.char_iter.next() {
+            Some('\\') => self.escaped_char(),
+            Some(c) => Ok(c),
+            None => Err(self
+                .error(RegexErrorKind::UnclosedClass, start)
+                .with_fix(self.insert_here("]"))),
+        }
+    }
+
+    /// Reads the rest of a bracket class like `[a-z_]` or `[^"]`, the opening
+    /// bracket having already been consumed.
+    ///
+    /// A `-` is literal at the start or end of the class, and `]` has to be
+    /// escaped to be a member. This makes `[]` the empty set, which matches
+    /// nothing, and `[^]` the set of all chars.
+    fn class(&mut self) -> Result<Token, RegexError> {
+        let start = self.offset() - 1;
+        let negated = self.char_iter.clone().next() == Some('^');
+        if negated {
+            self.char_iter.next();
+        }
+
+        let mut ranges = vec![];
+        // Out of order ranges are only reported once the whole class is read,
+        // so that the rest of it doesn't get read as the pattern after it.
+        let mut error = None;
+        loop {
+            let lo_start = self.offset();
+            let lo = match self.char_iter.clone().next() {
+                Some(']') => {
+                    self.char_iter.next();
+                    match error {
+                        Some(err) => return Err(err),
+                        None => break,
+                    }
+                }
+                Some('\\') => {
+                    self.char_iter.next();
+                    if let Some(set) = self.class_escape()? {
+                        ranges.extend_from_slice(set.ranges());
+                        continue;
+                    }
+                    self.escaped_char()?
+                }
+                _ => self.class_char(start)?,
+            };
+
+            let mut lookahead = self.char_iter.clone();
+            if lookahead.next() == Some('-') && !matches!(lookahead.next(), Some(']') | None) {
+                let dash = self.offset();
+                self.char_iter.next();
+                let hi = self.class_char(start)?;
+                if hi < lo {
+                    let err = self.error(RegexErrorKind::RangeOutOfOrder { lo, hi }, lo_start);
+                    let swapped = format!(
+                        "{}-{}",
+                        &self.string[dash + 1..err.span.end],
+                        &self.string[lo_start..dash]
+                    );
+                    let fix = self.fix(err.span, &swapped);
+                    error.get_or_insert(err.with_fix(fix));
+                    continue;
+                }
+                ranges.push((lo, hi));
+            } else {
+                ranges.push((lo, lo));
+            }
+        }
+
+        let set = CharSet::from_ranges(ranges);
+        Ok(Token::Class(if negated { self.negate(set) } else { set }))
+    }
+
+    /// Reads a decimal bound of a repetition, returning `None` if there are no
+    /// digits. Bounds too large for a `u32` saturate, and are then rejected by
+    /// the repetition limit.
+    fn repetition_bound(&mut self) -> Option<u32> {
+        let mut bound: Option<u32> = None;
+        while let Some(digit) = self.char_iter.clone().next().and_then(|c| c.to_digit(10)) {
+            self.char_iter.next();
+            bound = Some(bound.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+        }
+        bound
+    }
+
+    /// Reads the rest of a `{min}`, `{min,}` or `{min,max}` repetition, the
+    /// opening brace having already been consumed.
+    fn repetition(&mut self) -> Result<Token, RegexError> {
+        let start = self.offset() - 1;
+        self.repetition_bounds().map_err(|err| {
+            let fix = match err {
+                // Most likely meant to be a literal brace.
+                RepetitionError::Unclosed | RepetitionError::UnexpectedChar(_) => {
+                    Some(Fix::insert(start, "\\"))
+                }
+                RepetitionError::MissingBound => Some(Fix::insert(start + 1, "0")),
+                RepetitionError::InvertedBounds { min, max } => Some(self.fix(
+                    Span::new(start, self.offset()),
+                    &format!("{{{},{}}}", max, min),
+                )),
+                RepetitionError::ExceedsLimit { .. } => None,
+            };
+            let err = self.error(RegexErrorKind::Repetition(err), start);
+            match fix {
+                Some(fix) => err.with_fix(fix),
+                None => err,
+            }
+        })
+    }
+
+    fn repetition_bounds(&mut self) -> Result<Token, RepetitionError> {
+        let min = self.repetition_bound();
+        let max = match self.char_iter.next() {
+            Some('}') => min,
+            Some(',') => {
+                let max = self.repetition_bound();
+                match self.char_iter.next() {
+                    Some('}') => max,
+                    Some(c) => return Err(RepetitionError::UnexpectedChar(c)),
+                    None => return Err(RepetitionError::Unclosed),
+                }
+            }
+            Some(c) => return Err(RepetitionError::UnexpectedChar(c)),
+            None => return Err(RepetitionError::Unclosed),
+        };
+
+        let min = min.ok_or(RepetitionError::MissingBound)?;
+        if let Some(max) = max {
+            if max < min {
+                return Err(RepetitionError::InvertedBounds { min, max });
+            }
+        }
+        Ok(Token::Repeat { min, max })
+    }
+
+    /// Reads the rest of a flag group like `(?i)`, or the start of a scoped one
+    /// like `(?i:`, the opening `(?` having already been consumed. Flags after
+    /// a `-` are turned off.
+    ///
+    /// A group with unknown flags still reads as one with the known flags
+    /// only, and the error comes right after it. That way a scoped group still
+    /// opens, so that its `)` isn't left unbalanced, and an operator after a
+    /// flag group is read the same as if the flags were fine.
+    fn flags(&mut self) -> Result<Token, RegexError> {
+        let start = self.offset() - 2;
+        let mut changes = vec![];
+        let mut on = true;
+        // The group as written without the unknown flags.
+        let mut known = String::from("(?");
+        // Unknown flags are only reported once the whole group is read, so
+        // that the rest of it doesn't get read as the pattern after it.
+        let mut error = None;
+        let scoped = loop {
+            let flag_start = self.offset();
+            match self.char_iter.next() {
+                Some(')') => break false,
+                Some(':') => break true,
+                Some('-') if on => {
+                    on = false;
+                    known.push('-');
+                }
+                Some(c) => match Flag::from_char(c) {
+                    Some(flag) => {
+                        changes.push((flag, on));
+                        known.push(c);
+                    }
+                    None => {
+                        error.get_or_insert(self.error(RegexErrorKind::UnknownFlag(c), flag_start));
+                    }
+                },
+                None => {
+                    return Err(self
+                        .error(RegexErrorKind::UnclosedFlags, start)
+                        .with_fix(self.insert_here(")")))
+                }
+            }
+        };
+
+        let span = Span::new(start, self.offset());
+        if scoped {
+            // With no flags left, the group is still one that doesn't capture.
+            let known = if changes.is_empty() { "(?" } else { &known };
+            self.deferred = error.map(|err| err.with_fix(self.fix(span, &format!("{}:", known))));
+            return Ok(Token::OpenFlagGroup(changes));
+        }
+        let error = match error {
+            // With no flags left, the group is better off removed.
+            Some(err) if changes.is_empty() => Some(err.with_fix(self.fix(span, ""))),
+            Some(err) => Some(err.with_fix(self.fix(span, &format!("{})", known)))),
+            None if changes.is_empty() => {
+                let err = self.error(RegexErrorKind::EmptyFlags, start);
+                let fix = self.fix(err.span, "");
+                Some(err.with_fix(fix))
             }
+            None => None,
+        };
+        self.deferred = error;
+        Ok(Token::SetFlags(changes))
+    }
+
+    /// Reads the name of a named group up to the closing `>`, the leading `(?<`
+    /// having already been consumed. The name is checked by the parser, which
+    /// knows about the other groups.
+    ///
+    /// An unclosed name still opens the group, so that the parser closes the
+    /// group too.
+    fn group_name(&mut self) -> Token {
+        let start = self.offset() - 3;
+        let mut name = String::new();
+        loop {
+            match self.char_iter.next() {
+                Some('>') => return Token::OpenNamedGroup(name),
+                Some(c) => name.push(c),
+                None => {
+                    self.deferred = Some(
+                        self.error(RegexErrorKind::UnclosedGroupName, start)
+                            .with_fix(self.insert_here(">")),
+                    );
+                    return Token::OpenNamedGroup(name);
+                }
+            }
+        }
+    }
+
+    /// Reads the `{...}` part of a `\u{...}` escape, which started at byte
+    /// `start`.
+    ///
+    /// Where the escape is malformed, the fix writes the digits it has the way
+    /// they were likely meant. Escapes naming no char at all are replaced with
+    /// `\u{fffd}`, the replacement character.
+    fn unicode_escape(&mut self, start: usize) -> Result<char, RegexError> {
+        if self.char_iter.clone().next() != Some('{') {
+            // Either the braces were left out, as in `\u41`, or a literal `u`
+            // was meant.
+            let digits: String = self
+                .char_iter
+                .clone()
+                .take(6)
+                .take_while(|c| c.is_ascii_hexdigit())
+                .collect();
+            self.char_iter = self.char_iter.as_str()[digits.len()..].chars();
+            let err = self.error(RegexErrorKind::UnicodeEscapeMissingBrace, start);
+            let fix = match code_point(&digits) {
+                Some(_) => self.fix(err.span, &format!("\\u{{{}}}", digits)),
+                None => self.fix(Span::new(start, start + 1), ""),
+            };
+            return Err(err.with_fix(fix));
+        }
+        self.char_iter.next();
+
+        // Everything up to the `}` is taken as part of the escape.
+        let mut digits = String::new();
+        let mut invalid = None;
+        loop {
+            match self.char_iter.next() {
+                Some('}') => break,
+                Some(c) if c.is_ascii_hexdigit() => digits.push(c),
+                Some(c) => {
+                    invalid.get_or_insert(c);
+                }
+                None => {
+                    return Err(self
+                        .error(RegexErrorKind::UnclosedUnicodeEscape, start)
+                        .with_fix(self.insert_here("}")))
+                }
+            }
+        }
+
+        let kind = if let Some(c) = invalid {
+            RegexErrorKind::InvalidHexDigit(c)
+        } else if digits.is_empty() || digits.len() > 6 {
+            RegexErrorKind::HexDigitCount
         } else {
-            None
+            let code = u32::from_str_radix(&digits, 16).expect("at most six hex digits");
+            match char::from_u32(code) {
+                Some(c) => return Ok(c),
+                None => RegexErrorKind::InvalidCodePoint(code),
+            }
+        };
+        let err = self.error(kind, start);
+        let fix = match code_point(&digits) {
+            Some(c) => format!("\\u{{{:x}}}", u32::from(c)),
+            None => "\\u{fffd}".to_string(),
+        };
+        let fix = self.fix(err.span, &fix);
+        Err(err.with_fix(fix))
+    }
+}
+
+impl<'a> Iterator for RegexTokenizer<'a> {
+    type Item = Result<(Token, Span), RegexError>;
+
+    fn next(&mut self) -> Option<Result<(Token, Span), RegexError>> {
+        if let Some(err) = self.deferred.take() {
+            return Some(Err(err));
         }
+        let start = self.offset();
+        let c = self.char_iter.next()?;
+        let token = match c {
+            '*' => Ok(Token::Star),
+            '+' => Ok(Token::Plus),
+            '?' => Ok(Token::Question),
+            '{' => self.repetition(),
+            '[' => self.class(),
+            '.' => Ok(Token::Dot),
+            '^' => Ok(Token::Caret),
+            '$' => Ok(Token::Dollar),
+            '|' => Ok(Token::Pipe),
+            '&' => Ok(Token::Ampersand),
+            '~' => Ok(Token::Tilde),
+            '-' if self.char_iter.clone().next() == Some('-') => {
+                self.char_iter.next();
+                Ok(Token::DoubleDash)
+            }
+            '(' if self.char_iter.clone().next() == Some('?') => {
+                self.char_iter.next();
+                if self.char_iter.clone().next() == Some('<') {
+                    self.char_iter.next();
+                    Ok(self.group_name())
+                } else {
+                    self.flags()
+                }
+            }
+            '(' => Ok(Token::OpenParens),
+            ')' => Ok(Token::CloseParens),
+            '\\' => self.escape(),
+            _ => Ok(Token::Char(c)),
+        };
+        // A malformed class or escape is still an atom, standing for the empty
+        // string, so that an operator after it has something to apply to
+        // instead of being reported as well.
+        let token = match token {
+            Err(err) if matches!(c, '[' | '\\') => {
+                self.deferred = Some(err);
+                Ok(Token::Epsilon)
+            }
+            token => token,
+        };
+        let span = Span::new(start, self.offset());
+        Some(token.map(|token| (token, span)))
     }
 }
@@ -0,0 +1,99 @@
+//! Unicode data backing the Unicode-aware classes.
+
+use super::charset::CharSet;
+
+pub mod case_folding_simple;
+pub mod general_category;
+pub mod perl_decimal;
+pub mod perl_space;
+pub mod perl_word;
+pub mod script;
+
+/// Normalizes a property name for loose matching, so that `Greek`, `greek`
+/// and `GREEK` are all the same, as are `Decimal_Number` and `decimal number`.
+pub fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '_' | '-'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Looks up a value of a property in one of the generated tables, by any of
+/// its aliases.
+fn lookup(
+    by_name: &[(&str, &[(char, char)])],
+    aliases: &[(&str, &str)],
+    value: &str,
+) -> Option<CharSet> {
+    let value = normalize(value);
+    let (_, name) = aliases.iter().find(|(alias, _)| *alias == value)?;
+    let (_, ranges) = by_name.iter().find(|(other, _)| other == name)?;
+    Some(CharSet::from_ranges(ranges.iter().copied()))
+}
+
+/// Returns the set for a `\p{..}` class.
+///
+/// The name is either a general category like `L` or `Letter`, a script like
+/// `Greek` or `Grek`, or one of `Any` and `ASCII`. It can also be qualified
+/// with the property it belongs to, as in `gc=L` or `Script=Greek`.
+pub fn property_set(name: &str) -> Option<CharSet> {
+    let gc = |value| lookup(general_category::BY_NAME, general_category::ALIASES, value);
+    let sc = |value| lookup(script::BY_NAME, script::ALIASES, value);
+
+    if let Some((property, value)) = name.split_once(['=', ':']) {
+        return match normalize(property).as_str() {
+            "gc" | "generalcategory" => gc(value),
+            "sc" | "script" => sc(value),
+            _ => None,
+        };
+    }
+
+    match normalize(name).as_str() {
+        "any" => Some(CharSet::full()),
+        "ascii" => Some(CharSet::from_ranges([('\0', '\x7F')])),
+        _ => gc(name).or_else(|| sc(name)),
+    }
+}
+
+/// Returns the number of single char insertions, deletions and substitutions
+/// turning `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substituted = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substituted.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Returns the known property closest to `name`, as a suggestion for a name
+/// `property_set` doesn't know. Only names differing from `name` in at most a
+/// third of its chars are suggested, and qualified names are only matched
+/// against the values of their property.
+pub fn suggest_property(name: &str) -> Option<String> {
+    let (prefix, value, tables) = match name.split_once(['=', ':']) {
+        Some((property, value)) => {
+            let tables: &[_] = match normalize(property).as_str() {
+                "gc" | "generalcategory" => &[general_category::ALIASES],
+                "sc" | "script" => &[script::ALIASES],
+                _ => return None,
+            };
+            (&name[..name.len() - value.len()], value, tables)
+        }
+        None => ("", name, &[general_category::ALIASES, script::ALIASES][..]),
+    };
+
+    let value = normalize(value);
+    let (distance, suggestion) = tables
+        .iter()
+        .flat_map(|aliases| aliases.iter())
+        .map(|(alias, name)| (edit_distance(&value, alias), name))
+        .min_by_key(|(distance, _)| *distance)?;
+    (distance * 3 <= value.chars().count()).then(|| format!("{}{}", prefix, suggestion))
+}
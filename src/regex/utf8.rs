@@ -0,0 +1,68 @@
+use super::charset::CharSet;
+
+/// A sequence of byte ranges, matching every string of bytes whose first byte
+/// is in the first range, second byte in the second range, and so on.
+pub type Utf8Sequence = Vec<(u8, u8)>;
+
+/// Splits the chars in `set` into sequences of byte ranges matching exactly
+/// their UTF-8 encodings. The sequences never match the same bytes.
+///
+/// Eg. `[a-zé]` becomes `[a-z]` followed by `\xC3 \xA9`, and `[\u{80}-\u{10FFFF}]`
+/// becomes one sequence for each length of the encoding, with a few more
+/// for the ranges of the first byte that restrict the second one.
+pub fn utf8_sequences(set: &CharSet) -> Vec<Utf8Sequence> {
+    let mut sequences = vec![];
+    for &(lo, hi) in set.ranges() {
+        split_range(u32::from(lo), u32::from(hi), &mut sequences);
+    }
+    sequences
+}
+
+/// Splits the code points from `lo` to `hi` until the encodings of each part
+/// can be matched byte by byte, by ranges between the bytes of the encodings
+/// of its ends.
+fn split_range(lo: u32, hi: u32, sequences: &mut Vec<Utf8Sequence>) {
+    // Surrogates have no encoding, a range can only go around them.
+    if lo < 0xD800 && 0xDFFF < hi {
+        split_range(lo, 0xD7FF, sequences);
+        split_range(0xE000, hi, sequences);
+        return;
+    }
+    // Both ends need encodings of the same length.
+    for max in [0x7F, 0x7FF, 0xFFFF] {
+        if lo <= max && max < hi {
+            split_range(lo, max, sequences);
+            split_range(max + 1, hi, sequences);
+            return;
+        }
+    }
+    // Wherever the ends differ before the last `i` continuation bytes, those
+    // bytes have to run over their whole range of `\x80-\xBF` in between.
+    // Split off the parts at either end that don't.
+    for i in 1..4 {
+        let tail = (1 << (6 * i)) - 1;
+        if lo & !tail != hi & !tail {
+            if lo & tail != 0 {
+                split_range(lo, lo | tail, sequences);
+                split_range((lo | tail) + 1, hi, sequences);
+                return;
+            }
+            if hi & tail != tail {
+                split_range(lo, (hi & !tail) - 1, sequences);
+                split_range(hi & !tail, hi, sequences);
+                return;
+            }
+        }
+    }
+
+    let encode = |point: u32| {
+        let mut buf = [0; 4];
+        let len = char::from_u32(point)
+            .expect("surrogates are split off")
+            .encode_utf8(&mut buf)
+            .len();
+        (buf, len)
+    };
+    let ((lo, len), (hi, _)) = (encode(lo), encode(hi));
+    sequences.push(lo.into_iter().zip(hi).take(len).collect());
+}
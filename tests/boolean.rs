@@ -0,0 +1,96 @@
+//! Checks intersection `&`, difference `--` and complement `~`.
+
+mod common;
+
+use common::{full_match, same_language};
+use rregex::regex::{build_enfa, Config, RegexErrorKind, RegexOp};
+
+fn matches(pattern: &str, text: &str) -> bool {
+    full_match(pattern, &Config::default(), text)
+}
+
+#[test]
+fn intersection() {
+    assert!(matches("[a-z]+&.*x.*", "box"));
+    assert!(!matches("[a-z]+&.*x.*", "bog"));
+    assert!(!matches("[a-z]+&.*x.*", "Box"));
+    assert!(matches("(a|b)*&.*a.*&.*b.*", "ba"));
+    assert!(!matches("(a|b)*&.*a.*&.*b.*", "aa"));
+    assert!(same_language("a*&b*", "\\e"));
+    assert!(same_language("a&b", "[]"));
+    assert!(same_language("(ab)*&a*b*", "\\e|ab"));
+}
+
+#[test]
+fn difference() {
+    let keywords = "[a-z]+--(if|else)";
+    for word in ["i", "iff", "elses", "x"] {
+        assert!(matches(keywords, word), "{word:?}");
+    }
+    for word in ["if", "else", "", "If"] {
+        assert!(!matches(keywords, word), "{word:?}");
+    }
+    assert!(same_language("[a-z]+--a--b", "[a-z]+--(a|b)"));
+    assert!(same_language("a*--a*", "[]"));
+    assert!(same_language("a*--\\e", "a+"));
+}
+
+#[test]
+fn complement() {
+    assert!(!matches("~ab", "ab"));
+    for text in ["", "a", "abc", "ba"] {
+        assert!(matches("~ab", text), "{text:?}");
+    }
+    assert!(same_language("~~(ab|c)", "ab|c"));
+    assert!(same_language("~[]", "(?s:.)*"));
+    assert!(same_language("~(?s:.)*", "[]"));
+    assert!(same_language("[a-z]+&~(if|else)", "[a-z]+--(if|else)"));
+    // The complement is over every char, new lines included.
+    assert!(matches("~a", "\n"));
+}
+
+#[test]
+fn precedence() {
+    // Union binds the loosest, then intersection and difference, then
+    // complement, then concatenation.
+    assert!(same_language("a|b&c", "a|(?:b&c)"));
+    assert!(same_language("ab&a.", "(?:ab)&(?:a.)"));
+    assert!(same_language("~ab&a.", "(?:~(?:ab))&(?:a.)"));
+    assert!(same_language("a--b--c", "(?:a--b)--c"));
+    assert!(same_language("a*b--ab", "(?:a*b)--(?:ab)"));
+    // A single dash is a literal.
+    assert!(matches("a-b", "a-b"));
+    assert!(matches("a\\-\\-b", "a--b"));
+}
+
+#[test]
+fn boolean_operators_build_plain_automata() {
+    for pattern in ["[a-z]+&~(if|else)", "a*--a{2}", "~(ab)*", "(?:a&b)*c"] {
+        let enfa = build_enfa(pattern, &Config::default()).unwrap();
+        assert!(!enfa.has_looks(), "{pattern:?}");
+        let rep = enfa.to_fa_rep();
+        assert!(!rep.get_fin().is_empty(), "{pattern:?}");
+        let dfa = enfa.convert_to_nfa().minimized_dfa();
+        assert!(!dfa.to_fa_rep().get_fin().is_empty(), "{pattern:?}");
+    }
+}
+
+#[test]
+fn empty_operands_are_the_empty_string() {
+    // As with `a|`, a missing operand matches the empty string.
+    assert!(same_language("a*&", "\\e"));
+    assert!(same_language("--a", "\\e"));
+    assert!(same_language("~", "(?s:.)+"));
+    assert!(same_language("a|~", "(?s:.)+"));
+}
+
+#[test]
+fn anchors_in_operands_are_errors() {
+    let errs = build_enfa("[a-z]+&~(^if)", &Config::default()).unwrap_err();
+    let kinds: Vec<_> = errs.into_iter().map(|err| err.kind).collect();
+    assert_eq!(
+        kinds,
+        [RegexErrorKind::AnchorInOperand(RegexOp::Complement)]
+    );
+    assert!(build_enfa("^(?:[a-z]+&~(if))$", &Config::default()).is_ok());
+}
@@ -0,0 +1,79 @@
+//! Checks where `captures` reports each group to have matched.
+
+use rregex::regex::Regex;
+
+/// Returns the range of each group in the first match of `pattern` in
+/// `haystack`, by group number.
+fn groups(pattern: &str, haystack: &str) -> Vec<Option<(usize, usize)>> {
+    let regex = Regex::new(pattern).unwrap();
+    let caps = regex.captures(haystack).unwrap();
+    (0..regex.captures_len())
+        .map(|i| caps.get(i).map(|m| (m.start(), m.end())))
+        .collect()
+}
+
+#[test]
+fn groups_of_a_match() {
+    assert_eq!(
+        groups("(a+)(b)?c", "xaac"),
+        [Some((1, 4)), Some((1, 3)), None]
+    );
+    let regex = Regex::new("(?<year>[0-9]{4})-(?<month>[0-9]{2})").unwrap();
+    let caps = regex.captures("on 2024-05").unwrap();
+    assert_eq!(caps.name("year").unwrap().as_str(), "2024");
+    assert_eq!(caps.get(2).unwrap().as_str(), "05");
+    assert!(caps.name("day").is_none());
+    assert_eq!(
+        regex.capture_names().collect::<Vec<_>>(),
+        [None, Some("year"), Some("month")]
+    );
+    assert!(regex.captures("2024").is_none());
+}
+
+#[test]
+fn groups_take_the_last_iteration() {
+    assert_eq!(groups("(a|b)*", "ab"), [Some((0, 2)), Some((1, 2))]);
+    assert_eq!(groups("(a){2}", "aa"), [Some((0, 2)), Some((1, 2))]);
+}
+
+#[test]
+fn nested_groups_are_cleared_by_each_iteration() {
+    // The last iteration of group 1 took the `b` branch, so group 2 didn't
+    // take part in it.
+    assert_eq!(groups("((a)|b)*", "ab"), [Some((0, 2)), Some((1, 2)), None]);
+    assert_eq!(
+        groups("(a(b)?)+", "aba"),
+        [Some((0, 3)), Some((2, 3)), None]
+    );
+    assert_eq!(
+        groups("(a(b)?)+", "abab"),
+        [Some((0, 4)), Some((2, 4)), Some((3, 4))]
+    );
+    assert_eq!(
+        groups("((a)|(b))*", "abba"),
+        [Some((0, 4)), Some((3, 4)), Some((3, 4)), None]
+    );
+    assert_eq!(
+        groups("((a)(b)?)*c", "abac"),
+        [Some((0, 4)), Some((2, 3)), Some((2, 3)), None]
+    );
+}
+
+#[test]
+fn earlier_groups_are_preferred_leftmost_longest() {
+    // Group 1 takes the longest part it can, and the later groups make do
+    // with the rest.
+    assert_eq!(
+        groups("(a|ab)(c|bcd)(d*)", "abcd"),
+        [Some((0, 4)), Some((0, 2)), Some((2, 3)), Some((3, 4))]
+    );
+    assert_eq!(
+        groups("(a*)(a*)", "aa"),
+        [Some((0, 2)), Some((0, 2)), Some((2, 2))]
+    );
+    // A group taking part wins over one that doesn't.
+    assert_eq!(groups("(a)?a?", "a"), [Some((0, 1)), Some((0, 1))]);
+    assert_eq!(groups("a?(a)?", "a"), [Some((0, 1)), Some((0, 1))]);
+    // Then the earlier start wins over the longer match.
+    assert_eq!(groups("x?(xy|yyy)y*", "xyyy"), [Some((0, 4)), Some((0, 2))]);
+}
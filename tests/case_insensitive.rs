@@ -0,0 +1,74 @@
+//! Checks that negated classes leave out every case of their chars when
+//! matching regardless of case.
+
+use rregex::regex::{Config, Regex};
+
+/// Returns which of `texts` the class `class` matches as a whole, with the
+/// `i` flag on.
+fn matching<'a>(class: &str, config: &Config, texts: &[&'a str]) -> Vec<&'a str> {
+    let regex = Regex::with_config(&format!("^(?i){}$", class), config).unwrap();
+    texts
+        .iter()
+        .copied()
+        .filter(|text| regex.is_match(text))
+        .collect()
+}
+
+#[test]
+fn negated_bracket_class() {
+    let config = Config::default();
+    assert_eq!(matching("[^a]", &config, &["a", "A", "b", "B"]), ["b", "B"]);
+    // The Kelvin sign folds to `k` as well.
+    assert_eq!(
+        matching("[^k]", &config, &["k", "K", "\u{212a}", "x"]),
+        ["x"]
+    );
+    assert_eq!(matching("[^a-c]", &config, &["B", "b", "d"]), ["d"]);
+    assert_eq!(matching("[^[:]", &config, &["[", ":", "a"]), ["a"]);
+}
+
+#[test]
+fn negated_shorthand_classes() {
+    let unicode = Config::default();
+    let ascii = Config::default().unicode(false);
+    // `\w` without Unicode has `k` and `s`, whose case variants include the
+    // Kelvin sign and the long s.
+    for config in [&unicode, &ascii] {
+        assert_eq!(
+            matching("\\W", config, &["k", "K", "\u{212a}", "\u{17f}", "-"]),
+            ["-"]
+        );
+        assert_eq!(matching("\\D", config, &["1", "a", "A"]), ["a", "A"]);
+        assert_eq!(
+            matching("[^\\W]", config, &["k", "\u{212a}", "-"]),
+            ["k", "\u{212a}"]
+        );
+    }
+}
+
+#[test]
+fn negated_properties() {
+    let config = Config::default();
+    // Without regard to case, `\p{Lu}` matches lowercase letters too, so
+    // `\P{Lu}` matches neither.
+    assert_eq!(matching("\\p{Lu}", &config, &["a", "A", "1"]), ["a", "A"]);
+    assert_eq!(matching("\\P{Lu}", &config, &["a", "A", "1"]), ["1"]);
+    assert_eq!(
+        matching("\\P{Greek}", &config, &["\u{3c9}", "\u{3a9}", "w"]),
+        ["w"]
+    );
+}
+
+#[test]
+fn negated_class_follows_the_flags_in_effect() {
+    let config = Config::default();
+    let regex = Regex::with_config("^(?i:[^a])[^b]$", &config).unwrap();
+    assert!(regex.is_match("xB"));
+    assert!(!regex.is_match("Ax"));
+    assert!(!regex.is_match("xb"));
+
+    let regex = Regex::with_config("^[^a](?i)[^b]$", &config).unwrap();
+    assert!(regex.is_match("AC"));
+    assert!(!regex.is_match("aC"));
+    assert!(!regex.is_match("AB"));
+}
@@ -0,0 +1,48 @@
+//! Checks char sets, which label transitions with ranges of chars.
+
+use rregex::regex::{build_enfa, CharSet, Config};
+
+#[test]
+fn ranges_are_sorted_and_merged() {
+    let set = CharSet::from_ranges([('x', 'z'), ('a', 'c'), ('b', 'f'), ('g', 'h'), ('q', 'p')]);
+    assert_eq!(set.ranges(), [('a', 'h'), ('x', 'z')]);
+    // Ranges on both sides of the surrogate gap are adjacent.
+    let set = CharSet::from_ranges([('\u{E000}', '\u{E001}'), ('\u{D700}', '\u{D7FF}')]);
+    assert_eq!(set.ranges(), [('\u{D700}', '\u{E001}')]);
+    let set = CharSet::from_ranges([('\0', char::MAX), ('a', 'b')]);
+    assert_eq!(set.ranges(), [('\0', char::MAX)]);
+}
+
+#[test]
+fn set_operations() {
+    let lower = CharSet::from_ranges([('a', 'z')]);
+    let vowels = CharSet::from_ranges("aeiou".chars().map(|c| (c, c)));
+    let consonants = lower.difference(&vowels);
+    assert!(consonants.contains('b'));
+    assert!(!consonants.contains('e'));
+    assert_eq!(consonants.union(&vowels), lower);
+    assert!(consonants.intersection(&vowels).is_empty());
+    assert_eq!(lower.negate().negate(), lower);
+    assert_eq!(CharSet::new().negate(), CharSet::full());
+    assert!(!lower.negate().contains('q'));
+    assert!(lower.negate().contains(char::MAX));
+}
+
+#[test]
+fn huge_classes_stay_cheap() {
+    // Each of these would need a transition per char if labels were sets of
+    // single chars.
+    let cases = [
+        ("[^a]", 2, "\u{10ffff}", "a"),
+        ("[^a]+b", 3, "λ\u{10ffff}b", "ab"),
+        ("[\\u{80}-\\u{10ffff}]{8}", 9, "éééééééé", "ééééééé"),
+        ("[\\u{0}-\\u{10ffff}]*x", 2, "\u{e000}x", "x\u{e000}"),
+    ];
+    for (pattern, states, accepted, rejected) in cases {
+        let enfa = build_enfa(pattern, &Config::default()).unwrap();
+        let dfa = enfa.convert_to_nfa().minimized_dfa();
+        assert_eq!(dfa.state_count(), states, "{pattern:?}");
+        assert!(dfa.is_match(accepted), "{pattern:?}");
+        assert!(!dfa.is_match(rejected), "{pattern:?}");
+    }
+}
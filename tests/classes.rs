@@ -0,0 +1,71 @@
+//! Checks bracket classes, with ranges, negation and escapes.
+
+mod common;
+
+use common::full_match;
+use rregex::regex::{build_enfa, Config, RegexErrorKind, Span};
+
+fn matches(pattern: &str, text: &str) -> bool {
+    full_match(pattern, &Config::default(), text)
+}
+
+#[test]
+fn ranges_and_single_chars() {
+    for c in ["a", "m", "z", "0", "9", "_"] {
+        assert!(matches("[a-z0-9_]", c), "{c:?}");
+    }
+    for c in ["A", "-", "", "ab", "é"] {
+        assert!(!matches("[a-z0-9_]", c), "{c:?}");
+    }
+    assert!(matches("[ac-e]+", "acde"));
+    assert!(!matches("[ac-e]+", "b"));
+    // Overlapping ranges are merged.
+    assert!(matches("[a-fd-k]+", "afk"));
+}
+
+#[test]
+fn negated_classes() {
+    assert!(matches("\"[^\"]*\"", "\"it's\""));
+    assert!(!matches("\"[^\"]*\"", "\"a\"b\""));
+    assert!(matches("[^a-z]", "A"));
+    assert!(matches("[^a-z]", "λ"));
+    assert!(matches("[^a-z]", "\u{10ffff}"));
+    assert!(!matches("[^a-z]", "q"));
+    assert!(!matches("[^a-z]", ""));
+    // Anything but nothing is any char at all.
+    assert!(matches("[^]", "\n"));
+}
+
+#[test]
+fn literal_dashes_and_carets() {
+    assert!(matches("[-a]", "-"));
+    assert!(matches("[a-]", "-"));
+    assert!(!matches("[a-]", "b"));
+    assert!(matches("[a^]", "^"));
+    assert!(matches("[^^]", "a"));
+    assert!(!matches("[^^]", "^"));
+}
+
+#[test]
+fn class_is_a_single_transition() {
+    let enfa = build_enfa("[a-z0-9_]", &Config::default()).unwrap();
+    let edges = enfa.to_fa_rep().get_dot_str().matches("->").count();
+    assert_eq!(edges, 1);
+    let dfa = enfa.convert_to_nfa().minimized_dfa();
+    assert_eq!(dfa.state_count(), 2);
+}
+
+#[test]
+fn malformed_classes() {
+    let errs = build_enfa("a[bc", &Config::default()).unwrap_err();
+    assert_eq!(errs[0].kind, RegexErrorKind::UnclosedClass);
+    assert_eq!(errs[0].span.start, 1);
+    let errs = build_enfa("[z-a]", &Config::default()).unwrap_err();
+    assert_eq!(
+        (&errs[0].kind, errs[0].span),
+        (
+            &RegexErrorKind::RangeOutOfOrder { lo: 'z', hi: 'a' },
+            Span::new(1, 4)
+        )
+    );
+}
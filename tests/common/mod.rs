@@ -0,0 +1,51 @@
+//! Helpers shared by the integration tests.
+
+// Each test file only uses some of the helpers.
+#![allow(dead_code)]
+
+use rregex::regex::{build_enfa, Config};
+
+/// Every string of up to `max_len` units taken from `alphabet`, shortest
+/// first.
+pub fn strings(alphabet: &[&str], max_len: usize) -> Vec<String> {
+    let mut strings = vec![String::new()];
+    let mut last = vec![String::new()];
+    for _ in 0..max_len {
+        last = last
+            .iter()
+            .flat_map(|s| alphabet.iter().map(move |c| format!("{s}{c}")))
+            .collect();
+        strings.extend(last.iter().cloned());
+    }
+    strings
+}
+
+/// Returns whether `pattern` matches the whole of `text`, after checking that
+/// the ε-NFA and the minimal DFA agree on it.
+pub fn full_match(pattern: &str, config: &Config, text: &str) -> bool {
+    let enfa = build_enfa(pattern, config).unwrap();
+    let accepted = enfa.simulate(text);
+    let dfa = enfa.convert_to_nfa().minimized_dfa();
+    assert_eq!(dfa.is_match(text), accepted, "{pattern:?} on {text:?}");
+    accepted
+}
+
+/// Returns whether two patterns match the same strings, by checking that the
+/// difference of their minimal DFAs is empty both ways round.
+pub fn same_language(a: &str, b: &str) -> bool {
+    same_language_with(a, b, &Config::default())
+}
+
+/// Like `same_language`, but parses both patterns with `config`.
+pub fn same_language_with(a: &str, b: &str, config: &Config) -> bool {
+    let dfa = |pattern| {
+        build_enfa(pattern, config)
+            .unwrap()
+            .convert_to_nfa()
+            .minimized_dfa()
+    };
+    let (a, b) = (dfa(a), dfa(b));
+    let extra = a.clone().difference(b.clone());
+    let missing = b.difference(a);
+    extra.to_fa_rep().get_fin().is_empty() && missing.to_fa_rep().get_fin().is_empty()
+}
@@ -0,0 +1,134 @@
+//! Checks the dense table of a `Dfa` against the automata it is built from.
+
+mod common;
+
+use std::collections::BTreeSet;
+
+use rregex::regex::{build_enfa, Config, Dfa};
+
+const PATTERNS: &[&str] = &[
+    "",
+    "[]",
+    "ab|c",
+    "(a|b)*abb",
+    "[^a]+b?",
+    "\\w\\d|é+",
+    "(?i)k",
+    "~(ab)",
+    "^a|b$",
+];
+
+/// Every string of up to 4 chars over an alphabet with chars that no
+/// pattern mentions, which fall in the class without transitions.
+fn strings() -> Vec<String> {
+    let alphabet = ["a", "b", "c", "k", "é", "1", "z", "\u{212a}", "\u{10ffff}"];
+    common::strings(&alphabet, 4)
+}
+
+#[test]
+fn table_agrees_with_nfa_membership() {
+    let strings = strings();
+    for pattern in PATTERNS {
+        let nfa = build_enfa(pattern, &Config::default())
+            .unwrap()
+            .convert_to_nfa();
+        let dfas = [
+            ("determinize", nfa.clone().determinize()),
+            ("to_dfa", nfa.clone().minimized_dfa().to_dfa()),
+        ];
+        for (name, dfa) in &dfas {
+            for s in &strings {
+                let expected = nfa.simulate(s);
+                assert_eq!(dfa.is_match(s), expected, "{name} {pattern:?} on {s:?}");
+                // Walking the table by hand ends in the same state.
+                let state = s.chars().fold(dfa.start(), |u, c| dfa.next_state(u, c));
+                assert_eq!(dfa.is_final(state), expected, "{name} {pattern:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn byte_table_agrees_with_nfa_membership() {
+    for pattern in PATTERNS {
+        let enfa = build_enfa(pattern, &Config::default()).unwrap();
+        let dfa = enfa.to_utf8().convert_to_nfa().determinize();
+        for s in strings() {
+            assert_eq!(
+                dfa.is_match_bytes(s.as_bytes()),
+                enfa.simulate(&s),
+                "{pattern:?} on {s:?}"
+            );
+        }
+        // Bytes that aren't UTF-8 are never accepted, even by `~(ab)`.
+        assert!(!dfa.is_match_bytes(b"\xff"), "{pattern:?}");
+    }
+}
+
+#[test]
+fn dead_state_rejects_and_never_leaves() {
+    for pattern in PATTERNS {
+        let dfa = build_enfa(pattern, &Config::default())
+            .unwrap()
+            .convert_to_nfa()
+            .determinize();
+        let dead = dfa.dead_state();
+        assert!(dfa.is_dead(dead));
+        assert_eq!(usize::try_from(dead).unwrap(), dfa.state_count() - 1);
+        assert!(!dfa.is_final(dead), "{pattern:?}");
+        for c in ['a', 'é', 'z', '\0', '\u{10ffff}'] {
+            assert_eq!(dfa.next_state(dead, c), dead, "{pattern:?}");
+        }
+    }
+}
+
+/// Returns the nodes and the edges, with their labels, of a graph in DOT.
+fn parse_dot(dot: &str) -> (BTreeSet<String>, BTreeSet<(String, String, String)>) {
+    let mut nodes = BTreeSet::new();
+    let mut edges = BTreeSet::new();
+    for line in dot.lines().map(str::trim) {
+        let Some((head, label)) = line.split_once(" [ label = ") else {
+            continue;
+        };
+        match head.split_once(" -> ") {
+            Some((u, v)) => edges.insert((u.to_string(), v.to_string(), label.to_string())),
+            None => nodes.insert(head.to_string()),
+        };
+    }
+    (nodes, edges)
+}
+
+fn assert_hides_exactly_the_dead_state(pattern: &str, dfa: &Dfa) {
+    let dead = dfa.dead_state().to_string();
+    let full = dfa.to_fa_rep(false);
+    let hidden = dfa.to_fa_rep(true);
+    let (full_nodes, full_edges) = parse_dot(&full.get_dot_str());
+    let (hidden_nodes, hidden_edges) = parse_dot(&hidden.get_dot_str());
+
+    assert_eq!(full_nodes.len(), dfa.state_count(), "{pattern:?}");
+    let mut expected = full_nodes.clone();
+    expected.remove(&dead);
+    assert_eq!(hidden_nodes, expected, "{pattern:?}");
+
+    let expected: BTreeSet<_> = full_edges
+        .iter()
+        .filter(|(_, v, _)| *v != dead)
+        .cloned()
+        .collect();
+    assert_eq!(hidden_edges, expected, "{pattern:?}");
+    assert!(full_edges.iter().any(|(_, v, _)| *v == dead), "{pattern:?}");
+
+    assert_eq!(full.get_start(), hidden.get_start());
+    assert_eq!(full.get_fin(), hidden.get_fin());
+}
+
+#[test]
+fn hide_dead_drops_exactly_the_dead_state() {
+    for pattern in PATTERNS {
+        let nfa = build_enfa(pattern, &Config::default())
+            .unwrap()
+            .convert_to_nfa();
+        assert_hides_exactly_the_dead_state(pattern, &nfa.clone().determinize());
+        assert_hides_exactly_the_dead_state(pattern, &nfa.minimized_dfa().to_dfa());
+    }
+}
@@ -0,0 +1,48 @@
+//! Checks the dot and the alphabet automata work over.
+
+mod common;
+
+use common::full_match;
+use rregex::regex::{build_enfa, CharSet, Config};
+
+#[test]
+fn dot_matches_any_char_but_new_line() {
+    let config = Config::default();
+    for c in ["a", " ", "λ", "😀", "\0", "\r", "\u{10ffff}"] {
+        assert!(full_match(".", &config, c), "{c:?}");
+    }
+    assert!(!full_match(".", &config, "\n"));
+    assert!(!full_match(".", &config, ""));
+    assert!(!full_match(".", &config, "ab"));
+    assert!(full_match("a.c", &config, "aλc"));
+}
+
+#[test]
+fn dot_matches_new_line_when_asked() {
+    let config = Config::default().dot_matches_new_line(true);
+    assert!(full_match(".", &config, "\n"));
+    assert!(full_match("(?s).", &Config::default(), "\n"));
+    assert!(full_match("(?s:.).", &Config::default(), "\na"));
+    assert!(!full_match("(?s:.).", &Config::default(), "a\n"));
+    assert!(!full_match("(?-s).", &config, "\n"));
+}
+
+#[test]
+fn automata_know_their_alphabet() {
+    let nfa = build_enfa("ab", &Config::default())
+        .unwrap()
+        .convert_to_nfa();
+    assert_eq!(nfa.alphabet(), &CharSet::full());
+    // Negating a class is over the full alphabet, not only the chars the
+    // pattern mentions.
+    let dfa = build_enfa("[^a]", &Config::default())
+        .unwrap()
+        .convert_to_nfa()
+        .minimized_dfa();
+    assert_eq!(dfa.alphabet(), &CharSet::full());
+    assert!(dfa.is_match("\u{10ffff}"));
+    assert!(dfa.clone().complement().is_match("a"));
+    assert!(dfa.clone().complement().is_match(""));
+    assert!(dfa.clone().complement().is_match("\u{10ffff}\u{10ffff}"));
+    assert!(!dfa.complement().is_match("b"));
+}
@@ -0,0 +1,56 @@
+//! Checks the empty string and the empty language at every stage.
+
+mod common;
+
+use common::{full_match, same_language};
+use rregex::regex::{build_enfa, Config, Minimizer};
+
+fn matches(pattern: &str, text: &str) -> bool {
+    full_match(pattern, &Config::default(), text)
+}
+
+#[test]
+fn empty_regexes_match_the_empty_string() {
+    for pattern in ["", "\\e", "()", "(?:)", "|", "\\e*"] {
+        assert!(matches(pattern, ""), "{pattern:?}");
+        assert!(!matches(pattern, "a"), "{pattern:?}");
+    }
+    assert!(matches("a|", ""));
+    assert!(matches("a|", "a"));
+    assert!(matches("|a", "a"));
+    assert!(matches("a()b", "ab"));
+    assert!(same_language("a(\\e|b)", "ab?"));
+}
+
+#[test]
+fn empty_class_matches_nothing() {
+    for text in ["", "a", "[]"] {
+        assert!(!matches("[]", text), "{text:?}");
+        assert!(!matches("a[]", text), "{text:?}");
+    }
+    assert!(matches("a[]|b", "b"));
+    assert!(!matches("a[]|b", "a"));
+    assert!(matches("[]*", ""));
+}
+
+#[test]
+fn automata_without_final_states() {
+    for pattern in ["[]", "a[]b", "[]+", "~[^]*"] {
+        let enfa = build_enfa(pattern, &Config::default()).unwrap();
+        let mut nfa = enfa.convert_to_nfa();
+        nfa.remove_unreachable_nodes();
+        for fa in [nfa.clone().reverse(), nfa.clone().subset_construction()] {
+            assert!(!fa.simulate(""), "{pattern:?}");
+            assert!(!fa.simulate("ab"), "{pattern:?}");
+        }
+        // Minimizing drops the final states that can't be reached.
+        for minimizer in [Minimizer::Brzozowski, Minimizer::Hopcroft] {
+            let dfa = nfa.clone().minimized_dfa_with(minimizer);
+            assert!(dfa.to_fa_rep().get_fin().is_empty(), "{pattern:?}");
+            assert!(!dfa.simulate(""), "{pattern:?}");
+        }
+        let table = nfa.determinize();
+        assert!(!table.is_match(""), "{pattern:?}");
+        assert!(!table.is_match("ab"), "{pattern:?}");
+    }
+}
@@ -0,0 +1,139 @@
+//! Checks the errors reported for malformed patterns.
+
+use rregex::regex::{apply_fixes, build_enfa, Config, RegexError, RegexErrorKind, RegexOp, Span};
+
+#[test]
+fn build_enfa_returns_every_error() {
+    // The errors come back as Rust values, without going through JavaScript,
+    // in the order they appear in the pattern.
+    let errs = build_enfa("(a\\q", &Config::default()).unwrap_err();
+    let kinds: Vec<_> = errs.iter().map(|err| (&err.kind, err.span)).collect();
+    assert_eq!(
+        kinds,
+        [
+            (&RegexErrorKind::UnbalancedOpenParen, Span::new(0, 1)),
+            (&RegexErrorKind::UnknownEscape('q'), Span::new(2, 4)),
+        ]
+    );
+    assert!(build_enfa("(a)", &Config::default()).is_ok());
+}
+
+/// Returns the errors in `pattern` along with the pattern with their fixes
+/// made.
+fn fixed(pattern: &str) -> (Vec<RegexError>, String) {
+    let errs = build_enfa(pattern, &Config::default()).unwrap_err();
+    let fixed = apply_fixes(pattern, &errs);
+    (errs, fixed)
+}
+
+#[test]
+fn malformed_atom_keeps_its_operator() {
+    for pattern in [
+        "[z-a]+",
+        "\\q*",
+        "\\p{Nope}*",
+        "\\u{110000}+",
+        "\\ud+",
+        "x\\u{12",
+    ] {
+        let (errs, _) = fixed(pattern);
+        assert_eq!(errs.len(), 1, "{}: {:?}", pattern, errs);
+        assert!(
+            !matches!(errs[0].kind, RegexErrorKind::MissingOperand(_)),
+            "{}",
+            pattern
+        );
+    }
+}
+
+#[test]
+fn fixed_patterns_parse() {
+    let cases = [
+        ("[z-a]+", "[a-z]+"),
+        ("\\q*", "q*"),
+        ("\\p{Greak}+", "\\p{Greek}+"),
+        (
+            "\\p{sc=Latn}\\p{gc=Lo}\\p{Cyrilic}",
+            "\\p{sc=Latn}\\p{gc=Lo}\\p{Cyrillic}",
+        ),
+        ("\\u{110000}+", "\\u{fffd}+"),
+        ("\\u{d800}", "\\u{fffd}"),
+        ("\\ud+", "\\u{d}+"),
+        ("\\u41?", "\\u{41}?"),
+        ("\\uz", "uz"),
+        ("\\u{4G}*", "\\u{4}*"),
+        ("\\u{GG}", "\\u{fffd}"),
+        ("\\u{}", "\\u{fffd}"),
+        ("\\u{0000041}", "\\u{41}"),
+        ("(?x)*", ""),
+        ("a(?ix)*b", "a(?i)b"),
+        ("(?ix-y:a)*", "(?i-:a)*"),
+        ("(?x:a)", "(?:a)"),
+        ("a(?)b", "ab"),
+        ("(?<>a)", "(a)"),
+        ("(?<1st-name>a)", "(?<_1st_name>a)"),
+        ("(?<a>x)(?<a>y)(?<a>z)", "(?<a>x)(?<a2>y)(?<a3>z)"),
+        ("(?<ab", "(?<ab>)"),
+        ("a{2000}", "a{1000}"),
+        ("a{5,3000}", "a{5,1000}"),
+        ("a{2000,}", "a{1000,}"),
+        ("(a{100}){100}", "(a{10}){100}"),
+    ];
+    for (pattern, expected) in cases {
+        let (errs, fixed) = fixed(pattern);
+        assert!(
+            errs.iter().all(|err| err.fix.is_some()),
+            "{}: {:?}",
+            pattern,
+            errs
+        );
+        assert_eq!(fixed, expected, "{}", pattern);
+        assert!(
+            build_enfa(&fixed, &Config::default()).is_ok(),
+            "{} fixed to {}",
+            pattern,
+            fixed
+        );
+    }
+}
+
+#[test]
+fn limit_fix_accounts_for_outer_repetitions() {
+    let config = Config::default().repetition_limit(20);
+    let errs = build_enfa("(a{4}){6}", &config).unwrap_err();
+    assert_eq!(errs.len(), 1);
+    let fixed = apply_fixes("(a{4}){6}", &errs);
+    assert_eq!(fixed, "(a{3}){6}");
+    assert!(build_enfa(&fixed, &config).is_ok());
+}
+
+#[test]
+fn anchors_in_boolean_operands_are_rejected() {
+    let cases = [
+        ("x(?:^a&a)", RegexOp::Intersection, Span::new(4, 5)),
+        ("(?:^a&a)", RegexOp::Intersection, Span::new(3, 4)),
+        ("(?:a$&a)b", RegexOp::Intersection, Span::new(4, 5)),
+        ("a--(?m:b$)", RegexOp::Difference, Span::new(8, 9)),
+        ("~^a", RegexOp::Complement, Span::new(1, 2)),
+        // The innermost operator is the one reported.
+        ("(?:~(a$))&b", RegexOp::Complement, Span::new(6, 7)),
+    ];
+    for (pattern, op, span) in cases {
+        let errs = build_enfa(pattern, &Config::default()).unwrap_err();
+        let kinds: Vec<_> = errs.iter().map(|err| (&err.kind, err.span)).collect();
+        assert_eq!(
+            kinds,
+            [(&RegexErrorKind::AnchorInOperand(op), span)],
+            "{}",
+            pattern
+        );
+    }
+    // Outside of the operators, anchors are fine.
+    for pattern in ["^(?:a&a)$", "x^|(?:a--b)$", "(?:a&b)*^"] {
+        assert!(
+            build_enfa(pattern, &Config::default()).is_ok(),
+            "{}",
+            pattern
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! Checks backslash escapes, outside and inside of classes.
+
+mod common;
+
+use common::full_match;
+use rregex::regex::{build_enfa, Config, RegexErrorKind, Span};
+
+fn matches(pattern: &str, text: &str) -> bool {
+    full_match(pattern, &Config::default(), text)
+}
+
+fn errors(pattern: &str) -> Vec<(RegexErrorKind, Span)> {
+    build_enfa(pattern, &Config::default())
+        .unwrap_err()
+        .into_iter()
+        .map(|err| (err.kind, err.span))
+        .collect()
+}
+
+#[test]
+fn metachars_match_literally() {
+    assert!(matches("a\\*b", "a*b"));
+    assert!(!matches("a\\*b", "aab"));
+    assert!(!matches("a\\*b", "b"));
+    for c in "\\*+?{}[]().|&~-^$".chars() {
+        let pattern = format!("\\{c}");
+        assert!(matches(&pattern, &c.to_string()), "{pattern:?}");
+        assert!(!matches(&pattern, ""), "{pattern:?}");
+    }
+    assert!(matches("\\(a\\|b\\)", "(a|b)"));
+    assert!(!matches("\\(a\\|b\\)", "a"));
+}
+
+#[test]
+fn control_and_hex_escapes() {
+    assert!(matches("a\\nb", "a\nb"));
+    assert!(matches("\\t\\r", "\t\r"));
+    assert!(matches("\\\\", "\\"));
+    assert!(matches("\\u{41}\\u{3bb}\\u{1F600}", "Aλ😀"));
+    assert!(matches("\\u{000041}", "A"));
+    assert!(!matches("\\u{41}", "\\u{41}"));
+}
+
+#[test]
+fn escapes_inside_classes() {
+    assert!(matches("[\\]\\-]+", "]-]"));
+    assert!(!matches("[\\]\\-]", "a"));
+    assert!(matches("[\\n\\u{3bb}]", "λ"));
+    assert!(matches("[\\n\\u{3bb}]", "\n"));
+}
+
+#[test]
+fn malformed_escapes_are_errors() {
+    assert_eq!(
+        errors("ab\\"),
+        [(RegexErrorKind::TrailingBackslash, Span::new(2, 3))]
+    );
+    assert_eq!(
+        errors("a\\qb"),
+        [(RegexErrorKind::UnknownEscape('q'), Span::new(1, 3))]
+    );
+    assert_eq!(
+        errors("\\u41"),
+        [(RegexErrorKind::UnicodeEscapeMissingBrace, Span::new(0, 4))]
+    );
+    assert_eq!(
+        errors("\\u{41"),
+        [(RegexErrorKind::UnclosedUnicodeEscape, Span::new(0, 5))]
+    );
+    assert_eq!(errors("\\u{4g}")[0].0, RegexErrorKind::InvalidHexDigit('g'));
+    assert_eq!(errors("\\u{}")[0].0, RegexErrorKind::HexDigitCount);
+    assert_eq!(errors("\\u{1234567}")[0].0, RegexErrorKind::HexDigitCount);
+    assert_eq!(
+        errors("\\u{d800}")[0].0,
+        RegexErrorKind::InvalidCodePoint(0xd800)
+    );
+    assert_eq!(
+        errors("\\u{110000}")[0].0,
+        RegexErrorKind::InvalidCodePoint(0x110000)
+    );
+}
@@ -0,0 +1,97 @@
+//! Checks non-capturing groups and flag groups.
+
+mod common;
+
+use common::{full_match, same_language};
+use rregex::regex::{build_enfa, Config, Regex, RegexErrorKind, Span};
+
+fn matches(pattern: &str, text: &str) -> bool {
+    full_match(pattern, &Config::default(), text)
+}
+
+fn errors(pattern: &str) -> Vec<(RegexErrorKind, Span)> {
+    build_enfa(pattern, &Config::default())
+        .unwrap_err()
+        .into_iter()
+        .map(|err| (err.kind, err.span))
+        .collect()
+}
+
+#[test]
+fn non_capturing_groups() {
+    assert!(same_language("(?:ab)*", "(ab)*"));
+    assert!(matches("(?:a|b)c", "bc"));
+    assert!(!matches("(?:a|b)c", "b"));
+    assert_eq!(Regex::new("(?:a)(b)(?:c)").unwrap().captures_len(), 2);
+    assert_eq!(Regex::new("(?i:a)(?s:b)").unwrap().captures_len(), 1);
+    let regex = Regex::new("(?:x(a))(?:b)(c)").unwrap();
+    let caps = regex.captures("xabc").unwrap();
+    assert_eq!(caps.get(1).unwrap().as_str(), "a");
+    assert_eq!(caps.get(2).unwrap().as_str(), "c");
+}
+
+#[test]
+fn scoped_flags() {
+    assert!(matches("(?i:abc)d", "ABCd"));
+    assert!(matches("(?i:abc)d", "aBcd"));
+    assert!(!matches("(?i:abc)d", "abcD"));
+    assert!(matches("(?s:.)", "\n"));
+    assert!(!matches("(?s:.).", "\n\n"));
+    assert!(matches("(?i:a(?-i:b)c)", "AbC"));
+    assert!(!matches("(?i:a(?-i:b)c)", "ABC"));
+    assert!(matches("(?is:a.)", "A\n"));
+    assert!(matches("(?i-s:a.)", "Ab"));
+    assert!(!matches("(?i-s:a.)", "A\n"));
+}
+
+#[test]
+fn flags_last_to_the_end_of_the_group() {
+    assert!(matches("a(?i)b", "aB"));
+    assert!(!matches("a(?i)b", "AB"));
+    assert!(matches("((?i)a)b", "Ab"));
+    assert!(!matches("((?i)a)b", "AB"));
+    assert!(matches("(?i)a(?-i)b", "Ab"));
+    assert!(!matches("(?i)a(?-i)b", "AB"));
+    // A flag group inside one alternative reaches up to the end of the group
+    // and not just the alternative.
+    assert!(matches("(?:a(?i)b|c)", "C"));
+    assert!(matches("x(?i)", "x"));
+}
+
+#[test]
+fn flags_follow_the_config() {
+    let config = Config::default().case_insensitive(true);
+    assert!(full_match("ab", &config, "AB"));
+    assert!(full_match("a(?-i)b", &config, "Ab"));
+    assert!(!full_match("a(?-i)b", &config, "AB"));
+    let config = Config::default().multi_line(true);
+    assert!(!full_match("(?-m)a$\\n", &config, "a\n"));
+}
+
+#[test]
+fn malformed_flag_groups_are_errors() {
+    assert_eq!(
+        errors("a(?x)b"),
+        [(RegexErrorKind::UnknownFlag('x'), Span::new(3, 4))]
+    );
+    assert_eq!(
+        errors("(?iy:a)"),
+        [(RegexErrorKind::UnknownFlag('y'), Span::new(3, 4))]
+    );
+    assert_eq!(
+        errors("a(?)b"),
+        [(RegexErrorKind::EmptyFlags, Span::new(1, 4))]
+    );
+    assert_eq!(
+        errors("(?-)"),
+        [(RegexErrorKind::EmptyFlags, Span::new(0, 4))]
+    );
+    assert_eq!(
+        errors("a(?i"),
+        [(RegexErrorKind::UnclosedFlags, Span::new(1, 4))]
+    );
+    assert_eq!(
+        errors("(?i:a"),
+        [(RegexErrorKind::UnbalancedOpenParen, Span::new(0, 4))]
+    );
+}
@@ -0,0 +1,128 @@
+//! Checks the tokens a `Lexer` splits its input into.
+
+use std::ops::Range;
+
+use rregex::regex::{build_enfa, Config, Lexer};
+
+/// Returns the tokens of `input` as rule names and spans, with `"!"` in place
+/// of the name for errors.
+fn tokens(lexer: &Lexer, input: &str) -> Vec<(String, Range<usize>)> {
+    lexer
+        .tokens(input)
+        .map(|token| match token {
+            Ok((name, span)) => (name.to_string(), span),
+            Err(err) => ("!".to_string(), err.span()),
+        })
+        .collect()
+}
+
+fn token(name: &str, span: Range<usize>) -> (String, Range<usize>) {
+    (name.to_string(), span)
+}
+
+#[test]
+fn tokens_are_maximal_munch() {
+    let lexer = Lexer::new([
+        ("eq", "="),
+        ("eqeq", "=="),
+        ("num", "[0-9]+"),
+        ("float", "[0-9]+\\.[0-9]+"),
+        ("ws", " +"),
+    ])
+    .unwrap();
+    assert_eq!(
+        tokens(&lexer, "=== 12.5  7"),
+        [
+            token("eqeq", 0..2),
+            token("eq", 2..3),
+            token("ws", 3..4),
+            token("float", 4..8),
+            token("ws", 8..10),
+            token("num", 10..11),
+        ]
+    );
+    // The longest token is taken even when a shorter one would let the rest
+    // of the input be read without errors.
+    let lexer = Lexer::new([("ab", "ab"), ("a", "a"), ("bc", "bc")]).unwrap();
+    assert_eq!(tokens(&lexer, "abc"), [token("ab", 0..2), token("!", 2..3)]);
+}
+
+#[test]
+fn earlier_rule_wins_ties() {
+    let rules = [("if", "if"), ("ident", "[a-z]+")];
+    let lexer = Lexer::new(rules).unwrap();
+    assert_eq!(tokens(&lexer, "if"), [token("if", 0..2)]);
+    // A longer token still wins over an earlier rule.
+    assert_eq!(tokens(&lexer, "iffy"), [token("ident", 0..4)]);
+    assert_eq!(tokens(&lexer, "i"), [token("ident", 0..1)]);
+
+    // Listed the other way around, the keyword never wins.
+    let lexer = Lexer::new([("ident", "[a-z]+"), ("if", "if")]).unwrap();
+    assert_eq!(tokens(&lexer, "if"), [token("ident", 0..2)]);
+    assert_eq!(lexer.rule_names().collect::<Vec<_>>(), ["ident", "if"]);
+}
+
+#[test]
+fn errors_on_unmatched_input() {
+    let lexer = Lexer::new([("word", "[a-z]+"), ("ws", " ")]).unwrap();
+    assert_eq!(
+        tokens(&lexer, "ab1 €c"),
+        [
+            token("word", 0..2),
+            token("!", 2..3),
+            token("ws", 3..4),
+            // The error spans the whole char.
+            token("!", 4..7),
+            token("word", 7..8),
+        ]
+    );
+    let err = lexer.tokens("?").next().unwrap().unwrap_err();
+    assert_eq!(err.to_string(), "No rule matches at 0");
+    assert!(tokens(&lexer, "").is_empty());
+
+    // A rule matching the empty string makes no empty tokens.
+    let lexer = Lexer::new([("as", "a*")]).unwrap();
+    assert_eq!(tokens(&lexer, "aab"), [token("as", 0..2), token("!", 2..3)]);
+}
+
+#[test]
+fn rules_with_the_same_shape_stay_apart() {
+    // Plain minimization would merge the states after `a` and after `b`,
+    // which accept the same strings from there on.
+    let lexer = Lexer::new([("x", "a[0-9]*"), ("y", "b[0-9]*"), ("z", "c")]).unwrap();
+    assert_eq!(
+        tokens(&lexer, "a12b3cb"),
+        [
+            token("x", 0..3),
+            token("y", 3..5),
+            token("z", 5..6),
+            token("y", 6..7),
+        ]
+    );
+}
+
+#[test]
+fn minimization_by_label_keeps_labels_apart() {
+    let dfa = build_enfa("a|b|c", &Config::default())
+        .unwrap()
+        .convert_to_nfa()
+        .subset_construction();
+    let table = dfa.to_dfa();
+    let after = |c| usize::try_from(table.next_state(table.start(), c)).unwrap();
+    let mut labels = vec![None; dfa.state_count()];
+    labels[after('a')] = Some(0);
+    labels[after('b')] = Some(1);
+    labels[after('c')] = Some(0);
+
+    let plain = dfa.clone().minimized_dfa();
+    let (minimal, accepts) = dfa.minimized_dfa_by_label(&labels);
+    // The start state, and one accepting state for each label.
+    assert_eq!(plain.state_count(), 2);
+    assert_eq!(minimal.state_count(), 3);
+    let table = minimal.to_dfa();
+    let label = |c| accepts[usize::try_from(table.next_state(table.start(), c)).unwrap()];
+    assert_eq!(label('a'), Some(0));
+    assert_eq!(label('b'), Some(1));
+    assert_eq!(label('c'), Some(0));
+    assert_eq!(accepts[usize::try_from(table.start()).unwrap()], None);
+}
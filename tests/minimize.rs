@@ -0,0 +1,59 @@
+//! Checks Hopcroft's minimization against Brzozowski's beyond the state
+//! counts and sampled strings that `stages.rs` compares.
+
+use rregex::regex::{build_enfa, Config, Minimizer};
+
+const PATTERNS: &[&str] = &[
+    "",
+    "[]",
+    "a",
+    "a*b",
+    "(a|b)*abb",
+    "(a|b)*a(a|b){4}",
+    "x{2,5}y?",
+    "(a|ab)(c|bcd)(d*)",
+    "[a-c]+--ab",
+    "~(a|b*)",
+    "(aa|b)*&(a|bb)*",
+    "\\w+@\\w+\\.(com|org)",
+    "(?i)[k-s]+é",
+    "^ab|b$",
+    "(?m)^a$\\nb",
+    "\\p{Greek}+|[0-9]{1,3}",
+];
+
+#[test]
+fn same_language() {
+    for pattern in PATTERNS {
+        let nfa = build_enfa(pattern, &Config::default())
+            .unwrap()
+            .convert_to_nfa();
+        let brzozowski = nfa.clone().minimized_dfa_with(Minimizer::Brzozowski);
+        let hopcroft = nfa.minimized_dfa_with(Minimizer::Hopcroft);
+        // The difference of the two is empty both ways round.
+        let extra = hopcroft.clone().difference(brzozowski.clone());
+        let missing = brzozowski.difference(hopcroft);
+        assert!(
+            extra.to_fa_rep().get_fin().is_empty(),
+            "pattern {pattern:?}"
+        );
+        assert!(
+            missing.to_fa_rep().get_fin().is_empty(),
+            "pattern {pattern:?}"
+        );
+    }
+}
+
+#[test]
+fn hopcroft_on_a_blow_up() {
+    // The minimal DFA of `(a|b)*a(a|b){n}` needs a state for each of the last
+    // n + 1 chars.
+    for n in 0..6 {
+        let pattern = format!("(a|b)*a(a|b){{{n}}}");
+        let nfa = build_enfa(&pattern, &Config::default())
+            .unwrap()
+            .convert_to_nfa();
+        let dfa = nfa.minimized_dfa_with(Minimizer::Hopcroft);
+        assert_eq!(dfa.state_count(), 1 << (n + 1), "pattern {pattern:?}");
+    }
+}
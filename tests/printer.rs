@@ -0,0 +1,105 @@
+//! Checks printing a parsed regex back in the pattern syntax.
+
+mod common;
+
+use common::{same_language, same_language_with};
+use rregex::regex::{get_debug_postexpr_string, parse_regex, AstKind, Config, Span};
+
+fn print(pattern: &str) -> String {
+    parse_regex(pattern, &Config::default())
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn printing_round_trips() {
+    for pattern in [
+        "(a|b)*c",
+        "a|bc*",
+        "(ab)+",
+        "[a-c]x",
+        "a{2,3}|a{2,}",
+        "\\.\\*\\n\\t",
+        "(?:a|b)c",
+        "(?<n>a)(b)",
+        "a&b--c",
+        "~(ab)",
+        "(?s:.)",
+        "(?m)^a$",
+        "\\e|[]",
+        "(a*)*",
+        "λ\\u{1F600}",
+        "(?i:a(?-i)b)",
+        "\\d\\W[^\\s]",
+    ] {
+        let printed = print(pattern);
+        assert_eq!(print(&printed), printed, "{pattern:?}");
+        assert!(same_language(pattern, &printed), "{pattern:?}");
+    }
+}
+
+#[test]
+fn printing_uses_as_few_parentheses_as_possible() {
+    assert_eq!(print("((a)|(b))"), "((a)|(b))");
+    assert_eq!(print("(?:(?:a)b)"), "ab");
+    assert_eq!(print("(?:a|b)c"), "(?:a|b)c");
+    assert_eq!(print("(?:ab)*"), "(?:ab)*");
+    // Postfix operators stack, so nested ones need no group.
+    assert_eq!(print("(?:a*)*"), "a**");
+    assert!(same_language("a**", "a*"));
+    assert_eq!(print("(?:a+){2}"), "a+{2}");
+    assert_eq!(print("a|(?:b|c)"), "a|b|c");
+    assert_eq!(print("(?:a&b)|c"), "a&b|c");
+    assert_eq!(print("\\u{41}"), "A");
+}
+
+#[test]
+fn printing_only_mentions_changed_flags() {
+    assert_eq!(print("(?i)ab|c"), "(?i:ab)|(?i:c)");
+    assert_eq!(print("a(?i)b"), "a(?i:b)");
+    assert_eq!(print("(?i:a(?-i)b)"), "(?i:a(?-i:b))");
+    assert_eq!(print("(?m)^a$"), "(?m:^a$)");
+    // Flags which are already set change nothing.
+    assert_eq!(print("(?-i:ab)"), "ab");
+    assert_eq!(print("(?i:(?i:a))"), "(?i:a)");
+}
+
+#[test]
+fn to_pattern_reads_back_with_the_same_config() {
+    let config = Config::default().case_insensitive(true);
+    let ast = parse_regex("a(?-i:b)c", &config).unwrap();
+    let pattern = ast.to_pattern(&config);
+    assert_eq!(pattern, "a(?-i:b)c");
+    assert!(same_language_with("a(?-i:b)c", &pattern, &config));
+    assert_eq!(
+        parse_regex(&pattern, &config).unwrap().to_pattern(&config),
+        pattern
+    );
+}
+
+#[test]
+fn nodes_keep_their_spans() {
+    let ast = parse_regex("ab|c*", &Config::default()).unwrap();
+    assert_eq!(ast.span, Span::new(0, 5));
+    let AstKind::Union(alternatives) = &ast.kind else {
+        panic!("{ast:?}");
+    };
+    assert_eq!(alternatives[0].span, Span::new(0, 2));
+    assert_eq!(alternatives[1].span, Span::new(3, 5));
+    let AstKind::Star(c) = &alternatives[1].kind else {
+        panic!("{ast:?}");
+    };
+    assert_eq!(c.kind, AstKind::Char('c'));
+    assert_eq!(c.span, Span::new(3, 4));
+}
+
+#[test]
+fn postfix_view() {
+    let ast = parse_regex("ab|c*", &Config::default()).unwrap();
+    let postfix = format!("{:?}", ast.to_postfix());
+    assert_eq!(
+        postfix,
+        "[Char('a'), Char('b'), Op(Concat), Char('c'), Op(Star), Op(Union)]"
+    );
+    assert_eq!(get_debug_postexpr_string("ab|c*"), format!("Ok({postfix})"));
+}
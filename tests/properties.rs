@@ -0,0 +1,103 @@
+//! Checks the Unicode property classes `\p{..}` and `\P{..}`.
+
+mod common;
+
+use common::{full_match, same_language};
+use rregex::regex::{apply_fixes, build_enfa, Config, RegexErrorKind, Span};
+
+fn matches(pattern: &str, text: &str) -> bool {
+    full_match(pattern, &Config::default(), text)
+}
+
+#[test]
+fn general_categories() {
+    for c in ["a", "Z", "λ", "ж", "中"] {
+        assert!(matches("\\p{L}", c), "{c:?}");
+        assert!(!matches("\\P{L}", c), "{c:?}");
+    }
+    for c in ["1", " ", "-", "😀"] {
+        assert!(!matches("\\p{L}", c), "{c:?}");
+        assert!(matches("\\P{L}", c), "{c:?}");
+    }
+    assert!(matches("\\p{Lu}", "Λ"));
+    assert!(!matches("\\p{Lu}", "λ"));
+    assert!(matches("\\p{Nd}", "٣"));
+    assert!(!matches("\\p{Nd}", "Ⅳ"));
+    assert!(same_language("\\p{L}", "\\p{Letter}"));
+    assert!(same_language("\\p{L}", "\\pL"));
+    assert!(same_language("\\P{L}", "\\PL"));
+    assert!(same_language("\\p{Lu}", "\\p{Uppercase_Letter}"));
+}
+
+#[test]
+fn scripts() {
+    for c in ["α", "Ω", "ϐ"] {
+        assert!(matches("\\p{Greek}", c), "{c:?}");
+    }
+    for c in ["a", "ж", "1"] {
+        assert!(!matches("\\p{Greek}", c), "{c:?}");
+        assert!(matches("\\P{Greek}", c), "{c:?}");
+    }
+    assert!(matches("\\p{Cyrillic}+", "жизнь"));
+    assert!(same_language("\\p{Greek}", "\\p{Grek}"));
+}
+
+#[test]
+fn names_are_loose_and_can_be_qualified() {
+    assert!(same_language("\\p{Nd}", "\\p{decimal number}"));
+    assert!(same_language("\\p{Nd}", "\\p{Decimal-Number}"));
+    assert!(same_language("\\p{Nd}", "\\p{DECIMAL_NUMBER}"));
+    assert!(same_language("\\p{Lu}", "\\p{gc=Lu}"));
+    assert!(same_language(
+        "\\p{Lu}",
+        "\\p{General_Category=Uppercase_Letter}"
+    ));
+    assert!(same_language("\\p{Greek}", "\\p{sc=Grek}"));
+    assert!(same_language("\\p{Greek}", "\\p{Script:Greek}"));
+    assert!(same_language("\\p{ASCII}", "[\\u{0}-\\u{7f}]"));
+    assert!(same_language("\\p{Any}", "(?s:.)"));
+}
+
+#[test]
+fn properties_inside_brackets() {
+    assert!(matches("[\\p{Greek}\\d]+", "αβ12"));
+    assert!(!matches("[\\p{Greek}\\d]", "a"));
+    assert!(matches("[^\\p{L}]", "1"));
+    assert!(!matches("[^\\p{L}]", "a"));
+    assert!(matches("[\\P{L}]", "1"));
+    assert!(same_language("[\\p{Lu}\\p{Ll}]", "\\p{Lu}|\\p{Ll}"));
+}
+
+#[test]
+fn unknown_properties_are_errors() {
+    let errs = build_enfa("a\\p{Greak}", &Config::default()).unwrap_err();
+    assert_eq!(errs.len(), 1);
+    assert_eq!(
+        errs[0].kind,
+        RegexErrorKind::UnknownProperty("Greak".to_string())
+    );
+    assert_eq!(errs[0].span, Span::new(1, 10));
+    assert_eq!(apply_fixes("a\\p{Greak}", &errs), "a\\p{Greek}");
+
+    let errs = build_enfa("\\p{sc=Grk}", &Config::default()).unwrap_err();
+    assert_eq!(apply_fixes("\\p{sc=Grk}", &errs), "\\p{sc=Greek}");
+
+    // Nothing is close enough to suggest.
+    let errs = build_enfa("\\p{Klingon}", &Config::default()).unwrap_err();
+    assert!(errs[0].fix.is_none());
+    let errs = build_enfa("\\p{foo=L}", &Config::default()).unwrap_err();
+    assert_eq!(
+        errs[0].kind,
+        RegexErrorKind::UnknownProperty("foo=L".to_string())
+    );
+
+    let kinds = |pattern| {
+        build_enfa(pattern, &Config::default())
+            .unwrap_err()
+            .into_iter()
+            .map(|err| err.kind)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(kinds("a\\p"), [RegexErrorKind::TrailingProperty]);
+    assert_eq!(kinds("\\p{Greek"), [RegexErrorKind::UnclosedProperty]);
+}
@@ -0,0 +1,67 @@
+//! Checks the `+` and `?` quantifiers through every stage.
+
+mod common;
+
+use common::{full_match, same_language};
+use rregex::regex::{build_enfa, Config, RegexErrorKind, RegexOp};
+
+fn matches(pattern: &str, text: &str) -> bool {
+    full_match(pattern, &Config::default(), text)
+}
+
+fn state_count(pattern: &str) -> usize {
+    build_enfa(pattern, &Config::default())
+        .unwrap()
+        .convert_to_nfa()
+        .minimized_dfa()
+        .state_count()
+}
+
+#[test]
+fn plus_matches_one_or_more() {
+    assert!(!matches("a+", ""));
+    assert!(matches("a+", "a"));
+    assert!(matches("a+", "aaaa"));
+    assert!(!matches("a+", "aab"));
+    assert!(matches("(ab)+", "abab"));
+    assert!(!matches("(ab)+", "aba"));
+    assert!(same_language("a+", "aa*"));
+    assert!(same_language("(a|b)+", "(a|b)(a|b)*"));
+}
+
+#[test]
+fn optional_matches_zero_or_one() {
+    assert!(matches("ab?c", "ac"));
+    assert!(matches("ab?c", "abc"));
+    assert!(!matches("ab?c", "abbc"));
+    assert!(matches("(ab)?", ""));
+    assert!(!matches("(ab)?", "a"));
+    assert!(same_language("ab?", "a|ab"));
+}
+
+#[test]
+fn quantifiers_stack() {
+    assert!(same_language("a+?", "a*"));
+    assert!(same_language("a?+", "a*"));
+    assert!(same_language("a*+", "a*"));
+}
+
+#[test]
+fn minimal_dfas_stay_small() {
+    assert_eq!(state_count("a+"), 2);
+    assert_eq!(state_count("a?"), 2);
+    assert_eq!(state_count("ab?c"), 4);
+    assert_eq!(state_count("(a|b)+c?"), 3);
+}
+
+#[test]
+fn quantifier_without_operand() {
+    for (pattern, op) in [("+a", RegexOp::Plus), ("a|?", RegexOp::Optional)] {
+        let errs = build_enfa(pattern, &Config::default()).unwrap_err();
+        assert_eq!(
+            errs[0].kind,
+            RegexErrorKind::MissingOperand(op),
+            "{pattern:?}"
+        );
+    }
+}
@@ -0,0 +1,53 @@
+//! Checks that bounded repetitions are held to the repetition limit.
+
+use rregex::regex::{Config, Regex, RegexErrorKind, RepetitionError, Span};
+
+fn limit_error(pattern: &str, config: &Config) -> Option<(u32, Span)> {
+    let errs = Regex::with_config(pattern, config).err()?;
+    errs.iter().find_map(|err| match err.kind {
+        RegexErrorKind::Repetition(RepetitionError::ExceedsLimit { count, .. }) => {
+            Some((count, err.span))
+        }
+        _ => None,
+    })
+}
+
+#[test]
+fn single_count_at_the_limit() {
+    let config = Config::default().repetition_limit(20);
+    assert!(Regex::with_config("a{20}", &config).is_ok());
+    assert_eq!(limit_error("a{21}", &config), Some((21, Span::new(1, 5))));
+    assert_eq!(limit_error("a{2,21}", &config), Some((21, Span::new(1, 7))));
+}
+
+#[test]
+fn nested_counts_multiply() {
+    let config = Config::default().repetition_limit(20);
+    assert!(Regex::with_config("(a{4}){5}", &config).is_ok());
+    // The inner repetition is where the product goes over.
+    assert_eq!(
+        limit_error("(a{4}){6}", &config),
+        Some((24, Span::new(2, 5)))
+    );
+    assert_eq!(
+        limit_error("((a{2}){3}b){4}", &config),
+        Some((24, Span::new(3, 6)))
+    );
+    // Stars don't make copies, and sibling repetitions don't multiply.
+    assert!(Regex::with_config("(a{4}b{4})*{5}", &config).is_ok());
+}
+
+#[test]
+fn nested_repeat_is_rejected_before_building() {
+    // A million copies of `a` would take far too long to build.
+    let (count, _) = limit_error("(a{1000}){1000}", &Config::default()).unwrap();
+    assert_eq!(count, 1_000_000);
+    assert!(limit_error("(?:(?:a{1000}){1000}){1000}", &Config::default()).is_some());
+}
+
+#[test]
+fn nested_repeat_under_boolean_operators() {
+    let config = Config::default().repetition_limit(20);
+    assert!(limit_error("(a{5}&a*){5}", &config).is_some());
+    assert!(limit_error("(~a{5}){5}", &config).is_some());
+}
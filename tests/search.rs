@@ -0,0 +1,90 @@
+//! Checks the matches `find` and `find_iter` report.
+
+use rregex::regex::{BytesRegex, Regex};
+
+fn ranges(pattern: &str, haystack: &str) -> Vec<(usize, usize)> {
+    Regex::new(pattern)
+        .unwrap()
+        .find_iter(haystack)
+        .map(|m| (m.start(), m.end()))
+        .collect()
+}
+
+#[test]
+fn find_is_leftmost_longest() {
+    let regex = Regex::new("a+|b").unwrap();
+    let m = regex.find("xbaa").unwrap();
+    assert_eq!((m.start(), m.end(), m.as_str()), (1, 2, "b"));
+    assert!(regex.find("xyz").is_none());
+    assert!(!regex.is_match("xyz"));
+    assert!(regex.is_match("xyza"));
+
+    // The match ending the earliest isn't the leftmost one.
+    assert_eq!(ranges("abcd|c", "abcd"), [(0, 4)]);
+    assert_eq!(ranges("c|bcd|abcde", "abcdx"), [(1, 4)]);
+    assert_eq!(ranges("a*b|c", "aaac"), [(3, 4)]);
+    assert_eq!(ranges("(a|ab)(c|bcd)", "abcd"), [(0, 4)]);
+}
+
+#[test]
+fn find_iter_reports_matches_in_order() {
+    assert_eq!(ranges("ab", "abxabab"), [(0, 2), (3, 5), (5, 7)]);
+    assert_eq!(ranges("a|aa", "aaa"), [(0, 2), (2, 3)]);
+    assert_eq!(ranges("[0-9]+", "a1b22c333"), [(1, 2), (3, 5), (6, 9)]);
+    assert!(ranges("x", "aaa").is_empty());
+    assert!(ranges("x", "").is_empty());
+}
+
+#[test]
+fn empty_matches() {
+    // An empty match right where the previous match ended is skipped.
+    assert_eq!(ranges("a*", "baaab"), [(0, 0), (1, 4), (5, 5)]);
+    assert_eq!(ranges("a*", ""), [(0, 0)]);
+    assert_eq!(ranges("\\e", "ab"), [(0, 0), (1, 1), (2, 2)]);
+    assert_eq!(ranges("a|\\e", "ab"), [(0, 1), (2, 2)]);
+    let regex = Regex::new("x*").unwrap();
+    assert_eq!(regex.find("abc").map(|m| m.is_empty()), Some(true));
+}
+
+#[test]
+fn matches_after_multi_byte_chars() {
+    assert_eq!(ranges("a", "éa"), [(2, 3)]);
+    assert_eq!(ranges("a+", "λaλaa"), [(2, 3), (5, 7)]);
+    assert_eq!(ranges("é", "aéé"), [(1, 3), (3, 5)]);
+    // Empty matches only fall between chars.
+    assert_eq!(ranges("\\e", "é€"), [(0, 0), (2, 2), (5, 5)]);
+    assert_eq!(ranges("a*", "éaé"), [(0, 0), (2, 3), (5, 5)]);
+    let m = Regex::new("[^a]+").unwrap().find("a€λa").unwrap();
+    assert_eq!(m.as_str(), "€λ");
+}
+
+#[test]
+fn anchors_see_the_chars_around_a_match() {
+    assert_eq!(ranges("^a", "aaa"), [(0, 1)]);
+    assert_eq!(ranges("a$", "aaa"), [(2, 3)]);
+    assert_eq!(ranges("(?m)^a", "ba\na"), [(3, 4)]);
+    assert_eq!(ranges("(?m)a$", "a\nba"), [(0, 1), (3, 4)]);
+    assert_eq!(ranges("(?m)^", "a\n\nb"), [(0, 0), (2, 2), (3, 3)]);
+    assert_eq!(ranges("^$", ""), [(0, 0)]);
+    assert!(ranges("^$", "a").is_empty());
+}
+
+#[test]
+fn bytes_regex_finds_matches_around_invalid_bytes() {
+    let regex = BytesRegex::new("é+|a").unwrap();
+    let haystack = b"\xff\xc3\xa9\xc3\xa9\xc3a\x80";
+    let found: Vec<_> = regex
+        .find_iter(haystack)
+        .map(|m| (m.start(), m.end()))
+        .collect();
+    assert_eq!(found, [(1, 5), (6, 7)]);
+    assert!(regex.is_match(b"\xffa"));
+    assert!(!regex.is_match(b"\xff\xc3"));
+    // Empty matches can fall inside an encoding.
+    let found: Vec<_> = BytesRegex::new("\\e")
+        .unwrap()
+        .find_iter("é".as_bytes())
+        .map(|m| m.start())
+        .collect();
+    assert_eq!(found, [0, 1, 2]);
+}
@@ -0,0 +1,62 @@
+//! Checks which patterns of a `RegexSet` are reported to match.
+
+use rregex::regex::{Regex, RegexSet};
+
+#[test]
+fn overlapping_patterns_all_match() {
+    let patterns = ["a", "ab", "[a-z]+", "b$", "^ab", "(?:ab)*"];
+    let set = RegexSet::new(patterns).unwrap();
+    assert_eq!(set.len(), patterns.len());
+    assert_eq!(set.matches("ab"), [0, 1, 2, 3, 4, 5]);
+    assert_eq!(set.matches("xab"), [0, 1, 2, 3, 5]);
+    assert_eq!(set.matches("ba"), [0, 2, 5]);
+    assert_eq!(set.matches("AB"), [5]);
+    // The same pattern twice is reported twice.
+    let set = RegexSet::new(["a+", "a+", "a"]).unwrap();
+    assert_eq!(set.matches("baa"), [0, 1, 2]);
+}
+
+#[test]
+fn agrees_with_each_pattern_alone() {
+    let patterns = ["a", "ab", "[a-z]+", "b$", "^ab", "é|λ", "(?m)^b", "~(a|b)*"];
+    let set = RegexSet::new(patterns).unwrap();
+    let regexes: Vec<_> = patterns.iter().map(|p| Regex::new(p).unwrap()).collect();
+    for haystack in ["", "a", "ab", "ba", "b\nb", "xé", "ABλ", "a\nab"] {
+        let expected: Vec<_> = (0..patterns.len())
+            .filter(|&i| regexes[i].is_match(haystack))
+            .collect();
+        assert_eq!(set.matches(haystack), expected, "{haystack:?}");
+    }
+}
+
+#[test]
+fn never_matching_patterns() {
+    let set = RegexSet::new(["[]", "a&b", "a", "~[^]*", "x"]).unwrap();
+    assert_eq!(set.matches("ab"), [2]);
+    assert_eq!(set.matches(""), Vec::<usize>::new());
+    assert!(!set.is_match("bcd"));
+
+    let set = RegexSet::new(["[]", "a&b"]).unwrap();
+    for haystack in ["", "a", "ab", "[]"] {
+        assert!(set.matches(haystack).is_empty(), "{haystack:?}");
+    }
+    // Anchored patterns stop matching once the haystack starts otherwise.
+    let set = RegexSet::new(["^a", "^b"]).unwrap();
+    assert!(!set.is_match("cab"));
+    assert_eq!(set.matches("bab"), [1]);
+}
+
+#[test]
+fn empty_set() {
+    let set = RegexSet::new(Vec::<&str>::new()).unwrap();
+    assert!(set.is_empty());
+    assert!(!set.is_match(""));
+    assert!(!set.is_match("abc"));
+}
+
+#[test]
+fn first_bad_pattern_is_reported() {
+    let (i, errs) = RegexSet::new(["a", "(b", "c)"]).unwrap_err();
+    assert_eq!(i, 1);
+    assert!(!errs.is_empty());
+}
@@ -0,0 +1,84 @@
+//! Checks the shorthand classes `\d`, `\w`, `\s` and their negations.
+
+mod common;
+
+use common::{full_match, same_language, same_language_with};
+use rregex::regex::{build_enfa, Config};
+
+fn matches(pattern: &str, text: &str) -> bool {
+    full_match(pattern, &Config::default(), text)
+}
+
+fn ascii_matches(pattern: &str, text: &str) -> bool {
+    full_match(pattern, &Config::default().unicode(false), text)
+}
+
+#[test]
+fn shorthands_are_unicode_by_default() {
+    for c in ["0", "9", "٣", "９"] {
+        assert!(matches("\\d", c), "{c:?}");
+        assert!(!matches("\\D", c), "{c:?}");
+    }
+    for c in ["a", "Z", "_", "7", "λ", "é"] {
+        assert!(matches("\\w", c), "{c:?}");
+        assert!(!matches("\\W", c), "{c:?}");
+    }
+    for c in [
+        " ", "\t", "\n", "\u{b}", "\u{c}", "\r", "\u{a0}", "\u{2003}",
+    ] {
+        assert!(matches("\\s", c), "{c:?}");
+        assert!(!matches("\\S", c), "{c:?}");
+    }
+    for c in ["-", ".", "@"] {
+        assert!(!matches("\\w", c), "{c:?}");
+        assert!(matches("\\W", c), "{c:?}");
+    }
+    assert!(matches("\\d\\w\\s\\D\\W\\S", "1a x-b"));
+}
+
+#[test]
+fn shorthands_are_ascii_without_unicode() {
+    assert!(ascii_matches("\\d", "5"));
+    assert!(!ascii_matches("\\d", "٣"));
+    assert!(ascii_matches("\\D", "٣"));
+    assert!(ascii_matches("\\w", "_"));
+    assert!(!ascii_matches("\\w", "λ"));
+    assert!(ascii_matches("\\W", "λ"));
+    assert!(ascii_matches("\\s", "\u{b}"));
+    assert!(!ascii_matches("\\s", "\u{a0}"));
+    assert!(ascii_matches("\\S", "\u{a0}"));
+
+    let config = Config::default().unicode(false);
+    assert!(same_language_with("\\d", "[0-9]", &config));
+    assert!(same_language_with("\\w", "[0-9A-Za-z_]", &config));
+    assert!(same_language_with(
+        "\\s",
+        "[\\t\\n\\u{b}\\u{c}\\r ]",
+        &config
+    ));
+}
+
+#[test]
+fn shorthands_inside_brackets() {
+    assert!(matches("[\\d_]", "7"));
+    assert!(matches("[\\d_]", "_"));
+    assert!(!matches("[\\d_]", "a"));
+    assert!(matches("[^\\s]", "a"));
+    assert!(!matches("[^\\s]", " "));
+    assert!(matches("[\\D]", "a"));
+    assert!(!matches("[\\D]", "1"));
+    assert!(same_language("[\\w\\W]", "(?s:.)"));
+    assert!(same_language("[\\d\\s]+", "(\\d|\\s)+"));
+}
+
+#[test]
+fn shorthands_keep_the_dfa_small() {
+    // `\w` is hundreds of ranges, but only one edge between two states.
+    let dfa = build_enfa("\\w+@\\w+", &Config::default())
+        .unwrap()
+        .convert_to_nfa()
+        .minimized_dfa();
+    assert_eq!(dfa.state_count(), 4);
+    assert!(dfa.simulate("user_1@héllo"));
+    assert!(!dfa.simulate("@host"));
+}
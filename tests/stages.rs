@@ -0,0 +1,121 @@
+//! Checks that every stage of the pipeline, from the ε-NFA to the minimal
+//! DFA, accepts the same strings.
+
+mod common;
+
+use rregex::regex::{build_enfa, Config, Minimizer};
+
+const PATTERNS: &[&str] = &[
+    "",
+    "\\e",
+    "[]",
+    "[^]",
+    "a",
+    ".",
+    "(?s).",
+    "a*b+c?",
+    "(a|b)*abb",
+    "(a|b)*a(a|b){4}",
+    "x{2,5}y?",
+    "(a|ab)(c|bcd)(d*)",
+    "a{2,3}|b{2,}|c{0}",
+    "((a|b){2}){2}",
+    "(?i)k[^k]",
+    "(?i:ab)c",
+    "\\d\\w\\s|\\D\\W\\S",
+    "\\w+@\\w+\\.(com|org)",
+    "\\p{Greek}+|\\P{L}",
+    "é|λ+|[\\u{1}-\\u{7f}]",
+    "[a-c]+--ab",
+    "~(a|b*)",
+    "(aa|b)*&(a|bb)*",
+    "~a&~b",
+    "^ab|b$",
+    "^$",
+    "a^b|a$b",
+    "(?m)^a$\\nb",
+    "(?m)(^|\\n)+$",
+    "(?<x>a)(b)?(?:c)",
+];
+
+/// Every string of up to 3 chars over a small alphabet, with ASCII, multi-byte
+/// chars, case variants and newlines in it, and a few longer ones.
+fn strings() -> Vec<String> {
+    let alphabet = [
+        "a", "b", "c", "d", "k", "K", "\u{212a}", "é", "λ", "\n", " ", "0", "x", "y", "@",
+    ];
+    let mut strings = common::strings(&alphabet, 3);
+    let longer = [
+        "abab",
+        "aaabb",
+        "babb",
+        "abcdd",
+        "aaab",
+        "a\n\nb",
+        "kk\u{212a}",
+        "babbb",
+        "xxxxy",
+        "a@b.com",
+    ];
+    strings.extend(longer.iter().map(|s| s.to_string()));
+    strings
+}
+
+#[test]
+fn every_stage_accepts_the_same_strings() {
+    let strings = strings();
+    for pattern in PATTERNS {
+        let enfa = match build_enfa(pattern, &Config::default()) {
+            Ok(enfa) => enfa,
+            // A pattern the corpus gets wrong would check nothing.
+            Err(errs) => panic!("{pattern:?}: {errs:?}"),
+        };
+        let nfa = enfa.convert_to_nfa();
+        // Laid out as tables once, rather than by `Nfa::is_match` on every
+        // string.
+        let subsets = nfa.clone().subset_construction().to_dfa();
+        let dfa = nfa.clone().determinize();
+        let brzozowski = nfa
+            .clone()
+            .minimized_dfa_with(Minimizer::Brzozowski)
+            .to_dfa();
+        let hopcroft = nfa.clone().minimized_dfa_with(Minimizer::Hopcroft).to_dfa();
+        let bytes = enfa.to_utf8().convert_to_nfa().minimized_dfa().to_dfa();
+
+        for s in &strings {
+            let expected = enfa.simulate(s);
+            let stages = [
+                ("nfa", nfa.simulate(s)),
+                ("subset construction", subsets.is_match(s)),
+                ("dfa", dfa.is_match(s)),
+                ("brzozowski", brzozowski.is_match(s)),
+                ("hopcroft", hopcroft.is_match(s)),
+                ("utf-8", bytes.is_match_bytes(s.as_bytes())),
+            ];
+            for (stage, accepted) in stages {
+                assert_eq!(accepted, expected, "{stage} for {pattern:?} on {s:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn minimal_dfas_agree_in_size() {
+    for pattern in PATTERNS {
+        let nfa = build_enfa(pattern, &Config::default())
+            .unwrap()
+            .convert_to_nfa();
+        let brzozowski = nfa.clone().minimized_dfa_with(Minimizer::Brzozowski);
+        let hopcroft = nfa.clone().minimized_dfa_with(Minimizer::Hopcroft);
+        assert_eq!(
+            brzozowski.state_count(),
+            hopcroft.state_count(),
+            "{pattern:?}"
+        );
+        // A minimal DFA is no larger than any other DFA for the pattern.
+        assert!(
+            hopcroft.state_count() <= nfa.subset_construction().state_count(),
+            "{pattern:?}"
+        );
+    }
+}
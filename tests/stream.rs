@@ -0,0 +1,108 @@
+//! Checks that a `StreamMatcher` finds the same matches as `find_iter`, no
+//! matter how the text is split into chunks.
+
+use std::ops::Range;
+
+use rregex::regex::Regex;
+
+const CASES: &[(&str, &str)] = &[
+    ("ab", "xabyabab"),
+    ("a*", "baaab"),
+    ("\\e", "aé"),
+    ("é+", "aééλé"),
+    ("abcd|c", "abcdxcabc"),
+    ("[^a]+", "a€λa€"),
+    ("(?m)^a$", "a\nba\na"),
+    ("(?m)$", "é\n\nλ"),
+    ("^a|a$", "aaa"),
+    ("λ|λa*", "λaaλ€λa"),
+];
+
+fn find_all(regex: &Regex, text: &str) -> Vec<Range<usize>> {
+    regex.find_iter(text).map(|m| m.range()).collect()
+}
+
+/// Feeds `chunks` to a new matcher as bytes, then finishes it.
+fn stream<'a>(regex: &Regex, chunks: impl IntoIterator<Item = &'a [u8]>) -> Vec<Range<usize>> {
+    let mut matcher = regex.stream();
+    let mut matches = Vec::new();
+    for chunk in chunks {
+        matches.extend(matcher.feed_bytes(chunk));
+    }
+    matches.extend(matcher.finish());
+    matches
+}
+
+#[test]
+fn split_at_every_byte() {
+    for &(pattern, text) in CASES {
+        let regex = Regex::new(pattern).unwrap();
+        let expected = find_all(&regex, text);
+        let bytes = text.as_bytes();
+        // Splits inside of UTF-8 sequences and inside of matches too.
+        for i in 0..=bytes.len() {
+            let (a, b) = bytes.split_at(i);
+            assert_eq!(
+                stream(&regex, [a, b]),
+                expected,
+                "{pattern:?} on {text:?} split at {i}"
+            );
+            for j in i..=bytes.len() {
+                let (b, c) = (&bytes[i..j], &bytes[j..]);
+                assert_eq!(
+                    stream(&regex, [a, b, c]),
+                    expected,
+                    "{pattern:?} on {text:?} split at {i} and {j}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn one_byte_at_a_time() {
+    for &(pattern, text) in CASES {
+        let regex = Regex::new(pattern).unwrap();
+        let chunks = text.as_bytes().chunks(1);
+        assert_eq!(
+            stream(&regex, chunks),
+            find_all(&regex, text),
+            "{pattern:?}"
+        );
+    }
+}
+
+#[test]
+fn chars_strs_and_readers_agree() {
+    for &(pattern, text) in CASES {
+        let regex = Regex::new(pattern).unwrap();
+        let expected = find_all(&regex, text);
+
+        let mut matcher = regex.stream();
+        let mut matches = Vec::new();
+        for (i, c) in text.char_indices() {
+            match i % 2 {
+                0 => matches.extend(matcher.feed_chars([c])),
+                _ => matches.extend(matcher.feed_str(c.encode_utf8(&mut [0; 4]))),
+            }
+        }
+        matches.extend(matcher.finish());
+        assert_eq!(matches, expected, "{pattern:?}");
+
+        let mut matcher = regex.stream();
+        let mut matches = matcher.feed_reader(text.as_bytes()).unwrap();
+        matches.extend(matcher.finish());
+        assert_eq!(matches, expected, "{pattern:?}");
+    }
+}
+
+#[test]
+fn invalid_utf8_reads_as_replacement_chars() {
+    let regex = Regex::new("\u{fffd}+|a").unwrap();
+    let bytes = b"a\xff\xfeb\xc3";
+    // The offsets count each replacement char as the bytes it stands for.
+    for i in 0..=bytes.len() {
+        let (a, b) = bytes.split_at(i);
+        assert_eq!(stream(&regex, [a, b]), [0..1, 1..3, 4..5], "split at {i}");
+    }
+}
@@ -0,0 +1,135 @@
+//! Checks that patterns compiled to read UTF-8 match exactly the encodings of
+//! their chars.
+
+use rregex::regex::{utf8_sequences, BytesRegex, CharSet};
+
+/// Returns every string of bytes one of `set`'s sequences matches, sorted.
+fn encodings(set: &CharSet) -> Vec<Vec<u8>> {
+    let mut encodings = vec![];
+    for sequence in utf8_sequences(set) {
+        let mut prefixes = vec![vec![]];
+        for &(lo, hi) in &sequence {
+            prefixes = prefixes
+                .iter()
+                .flat_map(|prefix| {
+                    (lo..=hi).map(move |b| {
+                        let mut bytes: Vec<u8> = prefix.clone();
+                        bytes.push(b);
+                        bytes
+                    })
+                })
+                .collect();
+        }
+        encodings.extend(prefixes);
+    }
+    encodings.sort();
+    encodings
+}
+
+/// Checks that the sequences for `set` match the encoding of each of its
+/// chars once, and nothing else.
+fn assert_exact(set: &CharSet) {
+    let mut expected: Vec<_> = set
+        .chars()
+        .map(|c| c.encode_utf8(&mut [0; 4]).as_bytes().to_vec())
+        .collect();
+    expected.sort();
+    assert!(encodings(set) == expected, "{:?}", set.ranges());
+}
+
+fn range(lo: u32, hi: u32) -> CharSet {
+    CharSet::from_ranges([(char::from_u32(lo).unwrap(), char::from_u32(hi).unwrap())])
+}
+
+#[test]
+fn sequences_cover_the_boundaries_exactly() {
+    // The last and first code points of each length of encoding.
+    for (last, first) in [(0x7F, 0x80), (0x7FF, 0x800), (0xFFFF, 0x10000)] {
+        assert_exact(&range(last, last));
+        assert_exact(&range(first, first));
+        assert_exact(&range(last, first));
+        assert_exact(&range(last - 2, first + 2));
+        assert_exact(&range(0, first));
+        assert_exact(&range(last, 0x10FFFF));
+    }
+    // Where a continuation byte rolls over.
+    for point in [0x83F, 0x840, 0xFFF, 0x1000, 0x3FFFF, 0x40000] {
+        assert_exact(&range(point - 1, point + 1));
+    }
+    assert_exact(&range(0x10FFFF, 0x10FFFF));
+    assert_exact(&range(0x10FFF0, 0x10FFFF));
+    assert_exact(&range(0x100000, 0x10FFFF));
+}
+
+#[test]
+fn sequences_go_around_the_surrogates() {
+    assert_exact(&range(0xD7FF, 0xE000));
+    assert_exact(&range(0xD000, 0xEFFF));
+    assert_exact(&range(0xD7FF, 0xD7FF));
+    assert_exact(&range(0xE000, 0xE000));
+    // The encodings of the surrogates, \xED followed by \xA0-\xBF, are
+    // left out of the whole set too.
+    assert_exact(&CharSet::full());
+}
+
+#[test]
+fn sequences_of_scattered_sets() {
+    assert_exact(&CharSet::new());
+    assert_exact(&CharSet::from_ranges([
+        ('a', 'z'),
+        ('é', 'é'),
+        ('\u{7ff}', '\u{801}'),
+        ('\u{fffd}', '\u{10002}'),
+        ('\u{10fffe}', '\u{10ffff}'),
+    ]));
+}
+
+#[test]
+fn bytes_regex_rejects_invalid_utf8() {
+    for pattern in [
+        "^(?s).$",
+        "^[^a]$",
+        "^\\P{Greek}$",
+        "^[\\u{0}-\\u{10ffff}]$",
+    ] {
+        let regex = BytesRegex::new(pattern).unwrap();
+        let invalid: [&[u8]; 9] = [
+            // A lone continuation byte.
+            b"\x80",
+            // Overlong encodings of `/` and of U+7FF.
+            b"\xc0\xaf",
+            b"\xe0\x9f\xbf",
+            // A surrogate.
+            b"\xed\xa0\x80",
+            // Past U+10FFFF.
+            b"\xf4\x90\x80\x80",
+            b"\xf5\x80\x80\x80",
+            b"\xff",
+            // Cut short.
+            b"\xe2\x82",
+            b"\xf0\x9f\x98",
+        ];
+        for bytes in invalid {
+            assert!(!regex.is_match(bytes), "{pattern:?} on {bytes:x?}");
+        }
+        for valid in [
+            "\u{7f}",
+            "\u{80}",
+            "\u{7ff}",
+            "\u{800}",
+            "\u{d7ff}",
+            "\u{e000}",
+            "\u{10ffff}",
+        ] {
+            assert!(regex.is_match(valid.as_bytes()), "{pattern:?} on {valid:?}");
+        }
+    }
+
+    // Searching steps over the invalid bytes.
+    let regex = BytesRegex::new("(?s).").unwrap();
+    let found: Vec<_> = regex
+        .find_iter(b"\xc3\xa9\x80\xed\xa0\x80\xf4\x8f\xbf\xbf")
+        .map(|m| m.range())
+        .collect();
+    assert_eq!(found, [0..2, 6..10]);
+}
@@ -1,7 +1,13 @@
 'use client';
 import {useEffect, useState} from 'react';
 import dynamic from 'next/dynamic';
-import RegexForm from '@/components/RegexForm';
+import RegexErrors, {
+  RegexErrorInfo,
+  RegexFix,
+  applyFix,
+  toErrorInfo,
+} from '@/components/RegexErrors';
+import RegexForm, {FormValues} from '@/components/RegexForm';
 import {makeDotStrProper} from '@/utils/graphviz';
 const rregexPromise = import('rregex');
 const Graphviz = dynamic(() => import('graphviz-react'), {
@@ -29,15 +35,50 @@ const Graphviz = dynamic(() => import('graphviz-react'), {
   ),
 });
 
+type TestResult = {
+  text: string;
+  enfa: boolean;
+  nfa: boolean;
+  dfa: boolean;
+};
+
+function Verdict(props: {accepted: boolean}) {
+  return props.accepted ? (
+    <span className="text-green-700">accept</span>
+  ) : (
+    <span className="text-red-700">reject</span>
+  );
+}
+
 export default function Home() {
   const [enfaDotStr, setEnfaDotStr] = useState<string>('');
   const [nfaDotStr, setNfaDotStr] = useState<string>('');
   const [dfaDotStr, setDfaDotStr] = useState<string>('');
+  const [testResults, setTestResults] = useState<TestResult[]>([]);
+  const [formValues, setFormValues] = useState<FormValues>({
+    regex: '',
+    tests: '',
+  });
+  const [pattern, setPattern] = useState<string>('');
+  const [errors, setErrors] = useState<RegexErrorInfo[]>([]);
   const [width, setWidth] = useState<number | undefined>(undefined);
   const [height, setHeight] = useState<number | undefined>(undefined);
 
-  const submitHandler = async (regex: string) => {
+  const submitHandler = async (values: FormValues) => {
+    setFormValues(values);
+    const regex = values.regex;
+    const tests = values.tests === '' ? [] : values.tests.split('\n');
     const rregex = await rregexPromise;
+    const localErrors = rregex.get_regex_errors(regex).map(toErrorInfo);
+    setPattern(regex);
+    setErrors(localErrors);
+    if (localErrors.length > 0) {
+      setEnfaDotStr('');
+      setNfaDotStr('');
+      setDfaDotStr('');
+      setTestResults([]);
+      return;
+    }
     try {
       const enfa = rregex.get_enfa_from_regex(regex);
       const nfa = enfa.convert_to_nfa();
@@ -60,6 +101,16 @@ export default function Home() {
       setEnfaDotStr(localEnfaStr);
       setNfaDotStr(localNfaStr);
       setDfaDotStr(localDfaStr);
+      // Every stage should give the same answer, so showing all three makes
+      // a bug in one of them visible.
+      setTestResults(
+        tests.map(text => ({
+          text,
+          enfa: enfa.simulate(text),
+          nfa: nfa.simulate(text),
+          dfa: dfa.is_match(text),
+        }))
+      );
 
       dfa.free();
       nfa.free();
@@ -69,6 +120,16 @@ export default function Home() {
     }
   };
 
+  const fixHandler = async (fix: RegexFix) => {
+    await submitHandler({...formValues, regex: applyFix(pattern, fix)});
+  };
+
+  const fixAllHandler = async () => {
+    const rregex = await rregexPromise;
+    const regex = rregex.get_fixed_regex(pattern);
+    await submitHandler({...formValues, regex});
+  };
+
   useEffect(() => {
     setWidth((2 / 3) * window.innerWidth);
     setHeight((2 / 5) * window.innerHeight);
@@ -78,8 +139,54 @@ export default function Home() {
     <main className="container mx-auto">
       <h1 className="text-3xl">RRegex</h1>
       <div className="w-full max-w-sm">
-        <RegexForm regexHandler={submitHandler} />
+        <RegexForm values={formValues} regexHandler={submitHandler} />
       </div>
+      {errors.length > 0 && (
+        <>
+          <hr />
+          <h2 className="text-2xl mt-4">Errors</h2>
+          <RegexErrors
+            pattern={pattern}
+            errors={errors}
+            fixHandler={fixHandler}
+            fixAllHandler={fixAllHandler}
+          />
+        </>
+      )}
+      {testResults.length > 0 && (
+        <>
+          <hr />
+          <h2 className="text-2xl mt-4">Test strings</h2>
+          <table className="table-auto mb-6">
+            <thead>
+              <tr>
+                <th className="px-4 text-left">Text</th>
+                <th className="px-4">ε-NFA</th>
+                <th className="px-4">NFA</th>
+                <th className="px-4">DFA</th>
+              </tr>
+            </thead>
+            <tbody>
+              {testResults.map((result, i) => (
+                <tr key={i}>
+                  <td className="px-4 font-mono whitespace-pre">
+                    {JSON.stringify(result.text)}
+                  </td>
+                  <td className="px-4 text-center">
+                    <Verdict accepted={result.enfa} />
+                  </td>
+                  <td className="px-4 text-center">
+                    <Verdict accepted={result.nfa} />
+                  </td>
+                  <td className="px-4 text-center">
+                    <Verdict accepted={result.dfa} />
+                  </td>
+                </tr>
+              ))}
+            </tbody>
+          </table>
+        </>
+      )}
       {enfaDotStr && (
         <>
           <hr />
@@ -0,0 +1,117 @@
+import type {RegexErrorRep} from 'rregex';
+
+export type RegexFix = {
+  start: number;
+  end: number;
+  replacement: string;
+  message: string;
+};
+
+export type RegexErrorInfo = {
+  start: number;
+  end: number;
+  message: string;
+  help?: string;
+  fix?: RegexFix;
+};
+
+/** Returns `pattern` with the chars in the span of `fix` replaced. */
+export function applyFix(pattern: string, fix: RegexFix): string {
+  return pattern.slice(0, fix.start) + fix.replacement + pattern.slice(fix.end);
+}
+
+/**
+ * Copies an error out of wasm memory, freeing it. The offsets are in UTF-16
+ * code units, so they index straight into the JS string of the pattern.
+ */
+export function toErrorInfo(rep: RegexErrorRep): RegexErrorInfo {
+  const fix = rep.fix;
+  const info = {
+    start: rep.start,
+    end: rep.end,
+    message: rep.message,
+    help: rep.help,
+    fix: fix && {
+      start: fix.start,
+      end: fix.end,
+      replacement: fix.replacement,
+      message: fix.message,
+    },
+  };
+  fix?.free();
+  rep.free();
+  return info;
+}
+
+function ErrorSpan(props: {pattern: string; start: number; end: number}) {
+  const {pattern, start, end} = props;
+  return (
+    <code className="whitespace-pre">
+      {pattern.slice(0, start)}
+      <span className="bg-red-200 text-red-800 underline decoration-wavy">
+        {/* An empty span, like a missing `)`, is shown as a caret. */}
+        {start === end ? '‸' : pattern.slice(start, end)}
+      </span>
+      {pattern.slice(end)}
+    </code>
+  );
+}
+
+export type RegexErrorsProps = {
+  pattern: string;
+  errors: RegexErrorInfo[];
+  fixHandler: (fix: RegexFix) => void;
+  fixAllHandler: () => void;
+};
+
+const buttonClassName =
+  'bg-blue-500 hover:bg-blue-700 text-white text-sm py-1 px-2 rounded focus:outline-none focus:shadow-outline';
+
+function FixButton(props: {
+  fix: RegexFix;
+  fixHandler: (fix: RegexFix) => void;
+}) {
+  const {fix, fixHandler} = props;
+  return (
+    <button
+      className={buttonClassName}
+      type="button"
+      onClick={() => fixHandler(fix)}
+    >
+      {fix.message}
+    </button>
+  );
+}
+
+export default function RegexErrors(props: RegexErrorsProps) {
+  const {pattern, errors, fixHandler, fixAllHandler} = props;
+  return (
+    <div className="mb-6">
+      <ul>
+        {errors.map((error, i) => (
+          <li key={i} className="mb-2">
+            <ErrorSpan pattern={pattern} start={error.start} end={error.end} />
+            <p className="text-red-700">{error.message}</p>
+            {error.help && (
+              <p className="text-sm text-gray-600">{error.help}</p>
+            )}
+            {error.fix && (
+              <FixButton fix={error.fix} fixHandler={fixHandler} />
+            )}
+          </li>
+        ))}
+      </ul>
+      {/* Fixing one error may shift the spans of the others, so they are
+          all made at once on the Rust side. */}
+      {errors.filter(error => error.fix).length > 1 && (
+        <button
+          className={buttonClassName}
+          type="button"
+          onClick={fixAllHandler}
+        >
+          Apply every fix
+        </button>
+      )}
+    </div>
+  );
+}
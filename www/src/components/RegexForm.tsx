@@ -1,30 +1,33 @@
 'use client';
 import {Field, FieldProps, Form, Formik, FormikHelpers} from 'formik';
 
-export type RegexFormProps = {
-  regexHandler: (regex: string) => Promise<void>;
+export type FormValues = {
+  regex: string;
+  tests: string;
 };
 
-type FormValues = {
-  regex: string;
+export type RegexFormProps = {
+  // The values to show, which change when a fix is applied to the regex.
+  values: FormValues;
+  regexHandler: (values: FormValues) => Promise<void>;
 };
 
 export default function RegexForm(props: RegexFormProps) {
-  const {regexHandler} = props;
-  const initialValues: FormValues = {
-    regex: '',
-  };
+  const {values, regexHandler} = props;
   const handleSubmit = async (
     values: FormValues,
     helpers: FormikHelpers<FormValues>
   ) => {
-    const {regex} = values;
-    await regexHandler(regex);
+    await regexHandler(values);
     helpers.setSubmitting(false);
   };
 
   return (
-    <Formik initialValues={initialValues} onSubmit={handleSubmit}>
+    <Formik
+      initialValues={values}
+      enableReinitialize
+      onSubmit={handleSubmit}
+    >
       {() => (
         <Form className="bg-white shadow-md rounded px-8 pt-6 pb-8 mb-4">
           <Field name="regex">
@@ -45,6 +48,24 @@ export default function RegexForm(props: RegexFormProps) {
               </div>
             )}
           </Field>
+          <Field name="tests">
+            {({field, form}: FieldProps<string, FormValues>) => (
+              <div className="mb-4">
+                <label
+                  className="block text-gray-700 text-sm font-bold mb-2"
+                  htmlFor="tests"
+                >
+                  Test strings, one per line
+                </label>
+                <textarea
+                  className="shadow appearance-none border rounded w-full py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline"
+                  rows={4}
+                  disabled={form.isSubmitting}
+                  {...field}
+                />
+              </div>
+            )}
+          </Field>
           <div className="flex items-center justify-between">
             <button
               className="bg-blue-500 hover:bg-blue-700 text-white font-bold py-2 px-4 rounded focus:outline-none focus:shadow-outline"